@@ -0,0 +1,59 @@
+use crate::{RngError, SecureRng};
+
+// Unbiased Fisher-Yates shuffle of a 52-card deck. Each swap index comes from
+// gen_below, which rejects instead of reducing modulo, so every one of the 52!
+// orderings is equally likely.
+pub fn shuffle_deck(rng: &mut SecureRng, deck: &mut [u8; 52]) -> Result<(), RngError> {
+    for i in (1..deck.len()).rev() {
+        let j = rng.gen_below(i as u32 + 1)? as usize;
+        deck.swap(i, j);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_deck() -> [u8; 52] {
+        let mut deck = [0u8; 52];
+        for (i, card) in deck.iter_mut().enumerate() {
+            *card = i as u8;
+        }
+        deck
+    }
+
+    #[test]
+    fn test_shuffle_is_permutation() {
+        let mut rng = SecureRng::new();
+        let mut deck = new_deck();
+        shuffle_deck(&mut rng, &mut deck).unwrap();
+
+        let mut sorted = deck;
+        sorted.sort_unstable();
+        assert_eq!(sorted, new_deck());
+        assert_ne!(deck, new_deck());
+    }
+
+    #[test]
+    fn test_every_card_reaches_every_position() {
+        let mut rng = SecureRng::from_seed([16u8; 32]);
+        let shuffles = 52_000;
+        let mut counts = vec![[0u32; 52]; 52];
+
+        for _ in 0..shuffles {
+            let mut deck = new_deck();
+            shuffle_deck(&mut rng, &mut deck).unwrap();
+            for (position, &card) in deck.iter().enumerate() {
+                counts[card as usize][position] += 1;
+            }
+        }
+
+        // Expected 1000 per (card, position) cell, standard deviation ~31
+        for row in &counts {
+            for &count in row {
+                assert!((820..1180).contains(&count), "cell count {}", count);
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// ChaCha20 keystream generator (64-bit block counter, 64-bit nonce)
+
+use crate::sha256::Sha256;
+
+const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+#[derive(Clone)]
+pub(crate) struct ChaCha20 {
+    key: [u32; 8],
+    counter: u64,
+    nonce: [u32; 2],
+}
+
+impl ChaCha20 {
+    pub(crate) fn new(key: [u8; 32]) -> Self {
+        ChaCha20::with_nonce(key, 0, 0)
+    }
+
+    pub(crate) fn with_nonce(key: [u8; 32], counter: u64, nonce: u64) -> Self {
+        let mut words = [0u32; 8];
+        for (word, chunk) in words.iter_mut().zip(key.chunks_exact(4)) {
+            *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+
+        ChaCha20 {
+            key: words,
+            counter,
+            nonce: [nonce as u32, (nonce >> 32) as u32],
+        }
+    }
+
+    // Re-key from SHA-256(key || counter || nonce || material) and restart the counter
+    pub(crate) fn reseed(&mut self, material: &[u8]) {
+        let mut hasher = Sha256::new();
+        for word in self.key {
+            hasher.update(&word.to_le_bytes());
+        }
+        hasher.update(&self.counter.to_le_bytes());
+        hasher.update(&self.nonce[0].to_le_bytes());
+        hasher.update(&self.nonce[1].to_le_bytes());
+        hasher.update(material);
+
+        *self = ChaCha20::new(hasher.finalize());
+    }
+
+    // Fill the whole buffer with keystream; a trailing partial block is discarded
+    pub(crate) fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(64) {
+            let block = self.next_block();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+    }
+
+    fn next_block(&mut self) -> [u8; 64] {
+        let mut state = [0u32; 16];
+        state[..4].copy_from_slice(&CONSTANTS);
+        state[4..12].copy_from_slice(&self.key);
+        state[12] = self.counter as u32;
+        state[13] = (self.counter >> 32) as u32;
+        state[14] = self.nonce[0];
+        state[15] = self.nonce[1];
+
+        let mut working = state;
+        for _ in 0..10 {
+            quarter_round(&mut working, 0, 4, 8, 12);
+            quarter_round(&mut working, 1, 5, 9, 13);
+            quarter_round(&mut working, 2, 6, 10, 14);
+            quarter_round(&mut working, 3, 7, 11, 15);
+            quarter_round(&mut working, 0, 5, 10, 15);
+            quarter_round(&mut working, 1, 6, 11, 12);
+            quarter_round(&mut working, 2, 7, 8, 13);
+            quarter_round(&mut working, 3, 4, 9, 14);
+        }
+
+        let mut out = [0u8; 64];
+        for (i, chunk) in out.chunks_exact_mut(4).enumerate() {
+            chunk.copy_from_slice(&working[i].wrapping_add(state[i]).to_le_bytes());
+        }
+
+        self.counter = self.counter.wrapping_add(1);
+        out
+    }
+}
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_key_keystream() {
+        let mut chacha = ChaCha20::new([0u8; 32]);
+        let mut out = [0u8; 32];
+        chacha.fill(&mut out);
+
+        let expected = [
+            0x76, 0xb8, 0xe0, 0xad, 0xa0, 0xf1, 0x3d, 0x90, 0x40, 0x5d, 0x6a, 0xe5, 0x53, 0x86,
+            0xbd, 0x28, 0xbd, 0xd2, 0x19, 0xb8, 0xa0, 0x8d, 0xed, 0x1a, 0xa8, 0x36, 0xef, 0xcc,
+            0x8b, 0x77, 0x0d, 0xc7,
+        ];
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_rfc7539_block() {
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        // RFC 7539 2.3.2: block count 1, nonce 00000009 0000004a 00000000
+        let mut chacha = ChaCha20::with_nonce(key, 1 | (0x0900_0000 << 32), 0x4a00_0000);
+        let mut out = [0u8; 16];
+        chacha.fill(&mut out);
+
+        let expected = [
+            0x10, 0xf1, 0xe7, 0xe4, 0xd1, 0x3b, 0x59, 0x15, 0x50, 0x0f, 0xdd, 0x1f, 0xa3, 0x20,
+            0x71, 0xc4,
+        ];
+        assert_eq!(out, expected);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::stats::upper_incomplete_gamma;
+use crate::{RngError, SecureRng};
+
+// A continuous distribution the tester can sample and map back to uniform
+pub trait Distribution {
+    fn sample(&self, rng: &mut SecureRng) -> Result<f64, RngError>;
+
+    // Cumulative distribution function; cdf(sample) is uniform on [0, 1]
+    fn cdf(&self, x: f64) -> f64;
+}
+
+pub struct Normal {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+pub struct Exponential {
+    pub rate: f64,
+}
+
+pub struct Gamma {
+    pub shape: f64,
+    pub scale: f64,
+}
+
+impl Distribution for Normal {
+    fn sample(&self, rng: &mut SecureRng) -> Result<f64, RngError> {
+        rng.gen_normal(self.mean, self.std_dev)
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        let z = (x - self.mean) / (self.std_dev * std::f64::consts::SQRT_2);
+        // erfc(-z) / 2, with erfc(t) = Q(1/2, t^2) for t >= 0
+        let tail = 0.5 * upper_incomplete_gamma(0.5, z * z);
+        if z >= 0.0 {
+            1.0 - tail
+        } else {
+            tail
+        }
+    }
+}
+
+impl Distribution for Exponential {
+    fn sample(&self, rng: &mut SecureRng) -> Result<f64, RngError> {
+        rng.gen_exponential(self.rate)
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        if x <= 0.0 {
+            0.0
+        } else {
+            -(-self.rate * x).exp_m1()
+        }
+    }
+}
+
+impl Distribution for Gamma {
+    fn sample(&self, rng: &mut SecureRng) -> Result<f64, RngError> {
+        rng.gen_gamma(self.shape, self.scale)
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        if x <= 0.0 {
+            0.0
+        } else {
+            1.0 - upper_incomplete_gamma(self.shape, x / self.scale)
+        }
+    }
+}
+
+type Constructor = fn(&[f64]) -> Result<Box<dyn Distribution>, String>;
+
+// Names accepted by `parse_distribution`, with their parameter lists
+const REGISTRY: &[(&str, &str, Constructor)] = &[
+    ("normal", "mean,std_dev", |p| {
+        let [mean, std_dev] = expect_params(p)?;
+        finite("mean", mean)?;
+        positive("std_dev", std_dev)?;
+        Ok(Box::new(Normal { mean, std_dev }))
+    }),
+    ("exponential", "rate", |p| {
+        let [rate] = expect_params(p)?;
+        positive("rate", rate)?;
+        Ok(Box::new(Exponential { rate }))
+    }),
+    ("gamma", "shape,scale", |p| {
+        let [shape, scale] = expect_params(p)?;
+        positive("shape", shape)?;
+        positive("scale", scale)?;
+        Ok(Box::new(Gamma { shape, scale }))
+    }),
+];
+
+// Parse `name:p1,p2,...`, e.g. "normal:0.0,1.0" or "exponential:2.0"
+pub fn parse_distribution(spec: &str) -> Result<Box<dyn Distribution>, String> {
+    let (name, params) = spec.split_once(':').unwrap_or((spec, ""));
+
+    let (_, _, constructor) = REGISTRY.iter()
+        .find(|(registered, _, _)| *registered == name)
+        .ok_or_else(|| {
+            let known: Vec<String> = REGISTRY.iter()
+                .map(|(name, params, _)| format!("{}:{}", name, params))
+                .collect();
+            format!("unknown distribution {:?} (expected one of {})", name, known.join(", "))
+        })?;
+
+    let params = params.split(',')
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| p.trim().parse::<f64>().map_err(|_| format!("invalid parameter {:?}", p)))
+        .collect::<Result<Vec<f64>, String>>()?;
+
+    constructor(&params).map_err(|e| format!("{}: {}", name, e))
+}
+
+// Map a probability onto the u32 range so the uniform battery can be applied
+pub fn uniformize(probability: f64) -> u32 {
+    (probability.clamp(0.0, 1.0) * 4_294_967_296.0).min(u32::MAX as f64) as u32
+}
+
+fn expect_params<const N: usize>(params: &[f64]) -> Result<[f64; N], String> {
+    params.try_into()
+        .map_err(|_| format!("expected {} parameter(s), got {}", N, params.len()))
+}
+
+fn finite(name: &str, value: f64) -> Result<(), String> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("{} must be finite, got {}", name, value))
+    }
+}
+
+fn positive(name: &str, value: f64) -> Result<(), String> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(format!("{} must be positive and finite, got {}", name, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DistributionStats;
+
+    #[test]
+    fn test_parse_normal_spec() {
+        let normal = parse_distribution("normal:0.0,1.0").unwrap();
+        assert!((normal.cdf(0.0) - 0.5).abs() < 1e-12);
+        assert!((normal.cdf(1.0) - 0.841_344_746_068_542_9).abs() < 1e-9);
+        assert!((normal.cdf(-1.96) - 0.024_997_895_148_220_4).abs() < 1e-9);
+
+        let shifted = parse_distribution("normal:10,2").unwrap();
+        assert!((shifted.cdf(10.0) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_distribution("cauchy:1.0").is_err());
+        assert!(parse_distribution("normal:0.0").is_err());
+        assert!(parse_distribution("normal:0.0,-1.0").is_err());
+        assert!(parse_distribution("exponential:abc").is_err());
+        assert!(parse_distribution("gamma").is_err());
+        assert!(parse_distribution("gamma:inf,1").is_err());
+        assert!(parse_distribution("gamma:1,NaN").is_err());
+        assert!(parse_distribution("exponential:inf").is_err());
+        assert!(parse_distribution("normal:inf,1").is_err());
+        assert!(parse_distribution("normal:0,NaN").is_err());
+    }
+
+    #[test]
+    fn test_uniformized_samples_pass_battery() {
+        let mut rng = SecureRng::from_seed([15u8; 32]);
+        for spec in ["normal:0.0,1.0", "exponential:2.0", "gamma:2.5,2.0"] {
+            let distribution = parse_distribution(spec).unwrap();
+            let sample: Vec<u32> = (0..50_000)
+                .map(|_| uniformize(distribution.cdf(distribution.sample(&mut rng).unwrap())))
+                .collect();
+            let stats = DistributionStats::from_sample(&sample);
+            assert!(stats.chi_square_p_value > 1e-4, "{} chi-square p {}", spec, stats.chi_square_p_value);
+        }
+    }
+}
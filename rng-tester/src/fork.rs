@@ -0,0 +1,86 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+// Bumped in the child after every fork(). Comparing it against a saved copy
+// detects a fork with one atomic load instead of a getpid syscall per draw.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static HANDLER_INSTALLED: OnceLock<bool> = OnceLock::new();
+
+#[cfg(unix)]
+fn install_handler() -> bool {
+    use std::os::raw::c_int;
+
+    extern "C" {
+        fn pthread_atfork(
+            prepare: Option<extern "C" fn()>,
+            parent: Option<extern "C" fn()>,
+            child: Option<extern "C" fn()>,
+        ) -> c_int;
+    }
+
+    // Runs in the child only; a lock-free atomic add is async-signal-safe
+    extern "C" fn bump_generation() {
+        GENERATION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    unsafe { pthread_atfork(None, None, Some(bump_generation)) == 0 }
+}
+
+// No fork() to guard against
+#[cfg(not(unix))]
+fn install_handler() -> bool {
+    true
+}
+
+// Changes in a forked child and nowhere else. The atfork handler is installed
+// on first use, so call this before forking (building a SecureRng does). If
+// it could not be installed, the PID is used instead: slower, but still
+// different in the child.
+pub(crate) fn generation() -> u64 {
+    if *HANDLER_INSTALLED.get_or_init(install_handler) {
+        GENERATION.load(Ordering::Relaxed)
+    } else {
+        std::process::id() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_is_stable_without_fork() {
+        let first = generation();
+        assert_eq!(HANDLER_INSTALLED.get(), Some(&true));
+        assert_eq!(generation(), first);
+        assert_eq!(std::thread::spawn(generation).join().unwrap(), first);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_generation_changes_in_forked_child() {
+        use std::os::raw::c_int;
+
+        extern "C" {
+            fn fork() -> c_int;
+            fn waitpid(pid: c_int, status: *mut c_int, options: c_int) -> c_int;
+            fn _exit(status: c_int) -> !;
+        }
+
+        let parent = generation();
+        unsafe {
+            let pid = fork();
+            assert!(pid >= 0, "fork failed");
+            if pid == 0 {
+                // Only async-signal-safe work in the child: an atomic load and _exit
+                _exit(if generation() != parent { 0 } else { 1 });
+            }
+
+            let mut status = 0;
+            assert_eq!(waitpid(pid, &mut status, 0), pid);
+            // Exited normally (low 7 bits clear) with code 0
+            assert_eq!(status, 0, "child saw an unchanged fork generation");
+        }
+        assert_eq!(generation(), parent);
+    }
+}
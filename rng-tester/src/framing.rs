@@ -0,0 +1,32 @@
+use std::io::{self, Write};
+
+use crate::thread_rng::try_with_thread_rng;
+
+// Writes a random nonce frame: 2-byte big-endian length, then `n` random
+// bytes from the thread generator, assembled first so the writer sees a
+// single write_all
+pub fn write_framed_random<W: Write>(w: &mut W, n: u16) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(2 + n as usize);
+    frame.extend_from_slice(&n.to_be_bytes());
+    try_with_thread_rng(|rng| rng.append_bytes(&mut frame, n as usize))?;
+    w.write_all(&frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_framed_random() {
+        let mut out = Vec::new();
+        write_framed_random(&mut out, 300).unwrap();
+        write_framed_random(&mut out, 0).unwrap();
+        assert_eq!(out.len(), 2 + 300 + 2);
+
+        let len = u16::from_be_bytes([out[0], out[1]]) as usize;
+        assert_eq!(len, 300);
+        let (payload, rest) = out[2..].split_at(len);
+        assert!(payload.iter().any(|&b| b != 0));
+        assert_eq!(rest, [0, 0]);
+    }
+}
@@ -0,0 +1,44 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+// Last-resort entropy from timer jitter: how long a tiny fixed workload takes
+// varies with caches, interrupts and frequency scaling. The raw deltas carry
+// little entropy each, so many of them are folded into every output word.
+// This is NOT a substitute for the OS generator.
+#[derive(Clone)]
+pub(crate) struct JitterEntropy {
+    state: u64,
+}
+
+impl JitterEntropy {
+    pub(crate) fn new() -> Self {
+        JitterEntropy { state: 0 }
+    }
+
+    pub(crate) fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            for _ in 0..64 {
+                self.state = mix(self.state ^ measure_delta());
+            }
+            chunk.copy_from_slice(&self.state.to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+fn measure_delta() -> u64 {
+    let start = Instant::now();
+    let mut acc = 0u64;
+    for i in 0..16u64 {
+        acc = black_box(acc.wrapping_mul(31).wrapping_add(i));
+    }
+    black_box(acc);
+    start.elapsed().as_nanos() as u64
+}
+
+// SplitMix64 finalizer
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
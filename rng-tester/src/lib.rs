@@ -1,5 +1,38 @@
 use std::io;
 
+mod cards;
+mod chacha;
+mod distributions;
+mod fork;
+mod framing;
+mod jitter;
+mod report;
+mod secure_rng;
+mod sha256;
+mod stats;
+mod thread_rng;
+
+pub use secure_rng::{
+    rejection_threshold, Checkpoint, EntropySource, RangeSampler, RngError, SampleRange, SecureRng,
+    SecureRngBuilder,
+};
+pub use cards::shuffle_deck;
+pub use distributions::{
+    parse_distribution, uniformize, Distribution, Exponential, Gamma, Normal,
+};
+pub use framing::write_framed_random;
+pub use report::{
+    render_histogram, DistributionStats, FailureClass, FormattedStats, QualityReport, ReportFormat,
+};
+pub use stats::{
+    assert_distribution_moments, assert_uniform, assert_uniform_with, autocorrelation,
+    bit_run_lengths, byte_entropy, chi_square_statistic, longest_run_of_ones_test,
+    min_entropy_mcv, monobit_test, popcount_distribution, runs_z_score, P2Quantile,
+};
+pub use thread_rng::{random_f64, random_range, random_u32, seeded_global, with_thread_rng};
+
+use secure_rng::IoContext;
+
 // adding pub makes the function public
 pub fn get_random_u32() -> io::Result<u32> {
     let mut buf = [0u8; 4];
@@ -8,17 +41,17 @@ pub fn get_random_u32() -> io::Result<u32> {
 }
 
 #[cfg(unix)]
-fn get_random_bytes(buf: &mut [u8]) -> io::Result<()> {
+pub(crate) fn get_random_bytes(buf: &mut [u8]) -> Result<(), RngError> {
     use std::fs::File;
     use std::io::Read;
 
-    let mut file = File::open("/dev/urandom")?;
-    file.read_exact(buf)?;
+    let mut file = File::open("/dev/urandom").context("opening /dev/urandom")?;
+    file.read_exact(buf).context("reading /dev/urandom")?;
     Ok(())
 }
 
 #[cfg(windows)]
-fn get_random_bytes(buf: &mut [u8]) -> io::Result<()> {
+pub(crate) fn get_random_bytes(buf: &mut [u8]) -> Result<(), RngError> {
     use std::io::Error;
     use std::os::raw::{c_char, c_ulong};
     use std::ptr::null_mut;
@@ -61,16 +94,17 @@ fn get_random_bytes(buf: &mut [u8]) -> io::Result<()> {
             CRYPT_VERIFYCONTEXT,
         ) == 0
         {
-            return Err(Error::last_os_error());
+            return Err(Error::last_os_error()).context("acquiring CryptoAPI context");
         }
 
         if CryptGenRandom(h_prov, buf.len() as c_ulong, buf.as_mut_ptr()) == 0 {
+            let error = Error::last_os_error();
             CryptReleaseContext(h_prov, 0);
-            return Err(Error::last_os_error());
+            return Err(error).context("CryptGenRandom");
         }
 
         if CryptReleaseContext(h_prov, 0) == 0 {
-            return Err(Error::last_os_error());
+            return Err(Error::last_os_error()).context("releasing CryptoAPI context");
         }
 
         Ok(())
@@ -83,9 +117,8 @@ mod tests {
 
     #[test]
     fn test_random_generation() {
-        match get_random_u32() {
-            Ok(_) => assert!(true),
-            Err(e) => panic!("Failed to generate random number: {}", e),
+        if let Err(e) = get_random_u32() {
+            panic!("Failed to generate random number: {}", e);
         }
     }
 }
\ No newline at end of file
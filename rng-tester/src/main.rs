@@ -1,106 +1,179 @@
-use std::collections::HashMap;
+use std::env;
 use std::io;
 use std::time::Instant;
-use rng_tester::get_random_u32;
-
-fn get_test_numbers(count: usize) -> io::Result<Vec<u32>> {
-    let mut numbers = Vec::with_capacity(count);
-    for _ in 0..count {
-        numbers.push(get_random_u32()?);
-    }
-    Ok(numbers)
+use rng_tester::{
+    get_random_u32, parse_distribution, popcount_distribution, render_histogram, uniformize,
+    Distribution, DistributionStats, FailureClass, ReportFormat, SecureRng,
+};
+
+struct Options {
+    seed: Option<[u8; 32]>,
+    // Test `name:params` samples (mapped through their CDF) instead of raw u32s
+    distribution: Option<(String, Box<dyn Distribution>)>,
+    count: usize,
+    format: ReportFormat,
 }
 
-fn main() -> io::Result<()> {
-    println!("Running Random Number Generator Tests...\n");
-    
-    let sample_size = 100_000;
-    
-    let start_time = Instant::now();
-    println!("Generating {} random numbers...", sample_size);
-    let numbers = get_test_numbers(sample_size)?;
-    println!("Generation time: {:?}\n", start_time.elapsed());
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        seed: None,
+        distribution: None,
+        count: 100_000,
+        format: ReportFormat::Percent,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let hex = args.next().ok_or("--seed requires a hex value")?;
+                options.seed = Some(parse_seed(&hex)?);
+            }
+            "--distribution" => {
+                let spec = args.next().ok_or("--distribution requires name:params")?;
+                let distribution = parse_distribution(&spec)?;
+                options.distribution = Some((spec, distribution));
+            }
+            "--count" => {
+                let count = args.next().ok_or("--count requires a number")?;
+                options.count = count.parse()
+                    .ok()
+                    .filter(|&count| count >= 2)
+                    .ok_or_else(|| format!("--count must be an integer >= 2, got {:?}", count))?;
+            }
+            "--format" => {
+                let format = args.next().ok_or("--format requires percent, raw or both")?;
+                options.format = match format.as_str() {
+                    "percent" => ReportFormat::Percent,
+                    "raw" => ReportFormat::RawCounts,
+                    "both" => ReportFormat::Both,
+                    other => return Err(format!("unknown report format: {}", other)),
+                };
+            }
+            other => return Err(format!("unknown argument: {}", other)),
+        }
+    }
 
-    run_distribution_tests(&numbers);
-    run_bit_pattern_analysis(&numbers);
-    run_speed_test()?;
-    run_entropy_test(&numbers);
-    run_sequence_tests(&numbers);
+    Ok(options)
+}
 
-    if check_randomness_criteria(&numbers) {
-        println!("\n✅ All randomness criteria passed!");
-    } else {
-        println!("\n❌ Some randomness criteria failed!");
+// Up to 64 hex digits; shorter seeds are zero-padded on the right
+fn parse_seed(hex: &str) -> Result<[u8; 32], String> {
+    if !hex.is_ascii() || hex.is_empty() || hex.len() > 64 || !hex.len().is_multiple_of(2) {
+        return Err(format!("seed must be an even number of hex digits (2-64), got {:?}", hex));
     }
 
-    Ok(())
+    let mut seed = [0u8; 32];
+    for (i, byte) in seed.iter_mut().take(hex.len() / 2).enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| format!("invalid hex in seed: {:?}", hex))?;
+    }
+    Ok(seed)
 }
 
-fn run_distribution_tests(numbers: &[u32]) {
-    println!("=== Distribution Tests ===");
-
-    let mean = numbers.iter().map(|&x| x as f64).sum::<f64>() / numbers.len() as f64;
-    let expected_mean = (u32::MAX as f64) / 2.0;
-    
-    let variance = numbers.iter()
-        .map(|&x| {
-            let diff = x as f64 - mean;
-            diff * diff
-        })
-        .sum::<f64>() / numbers.len() as f64;
-    
-    let std_dev = variance.sqrt();
+fn format_seed(seed: &[u8; 32]) -> String {
+    seed.iter().map(|b| format!("{:02x}", b)).collect()
+}
 
-    println!("Mean: {:.2} (Expected: {:.2})", mean, expected_mean);
-    println!("Standard Deviation: {:.2}", std_dev);
-    
-    // Distribution across ranges
-    let mut ranges = vec![0; 10];
-    let range_size = (u32::MAX as f64) / 10.0;
-    
-    for &num in numbers {
-        let index = (num as f64 / range_size) as usize;
-        if index < 10 {
-            ranges[index] += 1;
+fn get_test_numbers(
+    count: usize,
+    seed: Option<[u8; 32]>,
+    distribution: Option<&dyn Distribution>,
+) -> io::Result<Vec<u32>> {
+    let mut numbers = Vec::with_capacity(count);
+    match (seed, distribution) {
+        (None, None) => {
+            for _ in 0..count {
+                numbers.push(get_random_u32()?);
+            }
+        }
+        (seed, None) => {
+            let mut rng = make_rng(seed);
+            for _ in 0..count {
+                numbers.push(rng.next_u32()?);
+            }
+        }
+        (seed, Some(distribution)) => {
+            let mut rng = make_rng(seed);
+            for _ in 0..count {
+                let x = distribution.sample(&mut rng)?;
+                numbers.push(uniformize(distribution.cdf(x)));
+            }
         }
     }
+    Ok(numbers)
+}
 
-    println!("\nDistribution across ranges:");
-    for (i, count) in ranges.iter().enumerate() {
-        let percentage = (*count as f64 / numbers.len() as f64) * 100.0;
-        println!("Range {}: {:.2}% (Expected: 10.00%)", i, percentage);
+fn make_rng(seed: Option<[u8; 32]>) -> SecureRng {
+    match seed {
+        Some(seed) => SecureRng::from_seed(seed),
+        None => SecureRng::new(),
     }
-    println!();
 }
 
-fn run_bit_pattern_analysis(numbers: &[u32]) {
-    println!("=== Bit Pattern Analysis ===");
+fn main() -> io::Result<()> {
+    let options = parse_args(env::args().skip(1))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
 
-    let mut bit_counts = vec![0; 32];
-    for &num in numbers {
-        for bit in 0..32 {
-            if (num & (1 << bit)) != 0 {
-                bit_counts[bit] += 1;
-            }
-        }
+    println!("Running Random Number Generator Tests...\n");
+
+    match &options.seed {
+        Some(seed) => println!("Source: seeded generator (seed {})\n", format_seed(seed)),
+        None => println!("Source: OS entropy\n"),
     }
+    if let Some((spec, _)) = &options.distribution {
+        println!("Distribution: {} (uniformized through its CDF)\n", spec);
+    }
+    
+    let sample_size = options.count;
+    
+    let start_time = Instant::now();
+    println!("Generating {} random numbers...", sample_size);
+    let distribution = options.distribution.as_ref().map(|(_, d)| d.as_ref());
+    let numbers = get_test_numbers(sample_size, options.seed, distribution)?;
+    println!("Generation time: {:?}\n", start_time.elapsed());
 
-    println!("Bit distribution (should be close to 50% for each bit):");
-    for (bit, &count) in bit_counts.iter().enumerate() {
-        let percentage = (count as f64 / numbers.len() as f64) * 100.0;
-        println!("Bit {}: {:.2}%", bit, percentage);
+    let stats = DistributionStats::from_sample(&numbers);
+    println!("{}", stats.with_format(options.format));
+    println!("=== Range Histogram ===");
+    println!("{}", render_histogram(&stats.range_counts, 50));
+    println!("=== Popcount Histogram (set bits per value) ===");
+    println!("{}", render_histogram(&popcount_distribution(&numbers), 50));
+    run_speed_test(options.seed)?;
+
+    match stats.failure_class() {
+        None => println!("✅ All randomness criteria passed!"),
+        Some(FailureClass::StatisticalWobble) => {
+            println!("❌ Some randomness criteria failed! (statistical deviation)")
+        }
+        Some(FailureClass::StuckSource) => {
+            println!("❌ Entropy source appears stuck: a bit never changes or values repeat!")
+        }
+        Some(FailureClass::InsufficientSample) => {
+            println!("⚠️ Sample too small to judge; use --count 64 or more")
+        }
     }
-    println!();
+
+    Ok(())
 }
 
-fn run_speed_test() -> io::Result<()> {
+fn run_speed_test(seed: Option<[u8; 32]>) -> io::Result<()> {
     println!("=== Speed Test ===");
     
     let iterations = 10_000;
     let start_time = Instant::now();
     
-    for _ in 0..iterations {
-        get_random_u32()?;
+    match seed {
+        Some(seed) => {
+            let mut rng = SecureRng::from_seed(seed);
+            for _ in 0..iterations {
+                rng.next_u32()?;
+            }
+        }
+        None => {
+            for _ in 0..iterations {
+                get_random_u32()?;
+            }
+        }
     }
     
     let elapsed = start_time.elapsed();
@@ -112,92 +185,61 @@ fn run_speed_test() -> io::Result<()> {
     Ok(())
 }
 
-fn run_entropy_test(numbers: &[u32]) {
-    println!("=== Entropy Analysis ===");
- 
-    let mut value_counts: HashMap<u32, usize> = HashMap::new();
-    for &num in numbers {
-        *value_counts.entry(num).or_insert(0) += 1;
-    }
-    
-    let total = numbers.len() as f64;
-    let entropy: f64 = value_counts.values()
-        .map(|&count| {
-            let probability = count as f64 / total;
-            -probability * probability.log2()
-        })
-        .sum();
-    
-    println!("Empirical entropy: {:.2} bits", entropy);
-    println!("Maximum possible entropy for u32: 32 bits");
-    println!("Entropy ratio: {:.2}%\n", (entropy / 32.0) * 100.0);
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn run_sequence_tests(numbers: &[u32]) {
-    println!("=== Sequence Analysis ===");
-    
+    #[test]
+    fn test_same_seed_reproduces_sample() {
+        let seed = parse_seed("00112233445566778899aabbccddeeff").unwrap();
+        let first = get_test_numbers(10_000, Some(seed), None).unwrap();
+        let second = get_test_numbers(10_000, Some(seed), None).unwrap();
 
-    let mut sum_diff = 0.0;
-    let mut sum_diff_squared = 0.0;
-    let len = numbers.len() - 1;
-    
-    for i in 0..len {
-        let diff = numbers[i + 1] as f64 - numbers[i] as f64;
-        sum_diff += diff;
-        sum_diff_squared += diff * diff;
+        let first_bytes: Vec<u8> = first.iter().flat_map(|n| n.to_ne_bytes()).collect();
+        let second_bytes: Vec<u8> = second.iter().flat_map(|n| n.to_ne_bytes()).collect();
+        assert_eq!(first_bytes, second_bytes);
     }
-    
-    let mean_diff = sum_diff / len as f64;
-    let variance_diff = (sum_diff_squared / len as f64) - (mean_diff * mean_diff);
-    
-    println!("Sequential difference analysis:");
-    println!("Mean difference between consecutive numbers: {:.2}", mean_diff);
-    println!("Variance of differences: {:.2}", variance_diff);
-    
 
-    let mut repeats = 0;
-    for i in 1..numbers.len() {
-        if numbers[i] == numbers[i-1] {
-            repeats += 1;
-        }
-    }
-    
-    let repeat_percentage = (repeats as f64 / numbers.len() as f64) * 100.0;
-    println!("Repeated numbers: {:.4}% (should be very close to 0%)\n", repeat_percentage);
-}
+    #[test]
+    fn test_parse_seed_flag() {
+        let args = ["--seed", "ff01"].iter().map(|s| s.to_string());
+        let options = parse_args(args).unwrap();
 
-fn check_randomness_criteria(numbers: &[u32]) -> bool {
-    let total = numbers.len() as f64;
-    
+        let mut expected = [0u8; 32];
+        expected[0] = 0xff;
+        expected[1] = 0x01;
+        assert_eq!(options.seed, Some(expected));
+        assert_eq!(format_seed(&expected)[..4], *"ff01");
 
-    let mut bit_counts = vec![0; 32];
-    for &num in numbers {
-        for bit in 0..32 {
-            if (num & (1 << bit)) != 0 {
-                bit_counts[bit] += 1;
-            }
-        }
-    }
-    
-    for &count in &bit_counts {
-        let percentage = (count as f64 / total) * 100.0;
-        if percentage < 48.0 || percentage > 52.0 {
-            return false;
-        }
-    }
-    
-    let mut repeats = 0;
-    for i in 1..numbers.len() {
-        if numbers[i] == numbers[i-1] {
-            repeats += 1;
-        }
-    }
-    
-    let repeat_percentage = (repeats as f64 / total) * 100.0;
-    if repeat_percentage > 0.1 {
-        return false;
+        assert!(parse_seed("abc").is_err());
+        assert!(parse_seed("zz").is_err());
+        assert!(parse_args(["--seed"].iter().map(|s| s.to_string())).is_err());
     }
 
-    
-    true
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_distribution_flags() {
+        let args = ["--distribution", "normal:0.0,1.0", "--count", "5000"]
+            .iter()
+            .map(|s| s.to_string());
+        let options = parse_args(args).unwrap();
+        assert_eq!(options.count, 5000);
+
+        let (spec, distribution) = options.distribution.unwrap();
+        assert_eq!(spec, "normal:0.0,1.0");
+        assert!((distribution.cdf(0.0) - 0.5).abs() < 1e-12);
+
+        let numbers = get_test_numbers(5000, Some([1u8; 32]), Some(distribution.as_ref())).unwrap();
+        assert_eq!(numbers.len(), 5000);
+
+        let bad = |args: &[&str]| parse_args(args.iter().map(|s| s.to_string())).is_err();
+        assert!(bad(&["--distribution", "unknown:1"]));
+        assert!(bad(&["--distribution", "gamma:inf,1"]));
+        assert!(bad(&["--distribution", "exponential:NaN"]));
+        assert!(bad(&["--count", "many"]));
+        assert!(bad(&["--count", "1"]));
+        assert!(bad(&["--format", "fractions"]));
+
+        let args = ["--format", "both"].iter().map(|s| s.to_string());
+        assert_eq!(parse_args(args).unwrap().format, ReportFormat::Both);
+    }
+}
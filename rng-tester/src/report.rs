@@ -0,0 +1,424 @@
+use std::fmt;
+
+use crate::stats::{
+    autocorrelation, byte_entropy, chi_square_p_value, chi_square_statistic, monobit_test,
+    runs_z_score, two_sided_p_value,
+};
+
+// Smallest sample the battery will judge. A healthy source leaves one of the
+// 32 bits constant across n values with probability about 64 / 2^n, so a
+// constant bit only signals a stuck source once n is this large.
+const MIN_JUDGED_SAMPLE: usize = 64;
+
+// Everything the tester battery measures about a sample of u32 values. The
+// Display impl renders the same text report the rng-tester binary prints.
+#[derive(Clone, Debug)]
+pub struct DistributionStats {
+    pub sample_size: usize,
+    pub mean: f64,
+    pub expected_mean: f64,
+    pub std_dev: f64,
+    // Counts per tenth of the u32 range
+    pub range_counts: [u64; 10],
+    // How many values have each bit set, and the same as a percentage
+    pub bit_counts: [u64; 32],
+    pub bit_frequencies: [f64; 32],
+    // Byte-frequency entropy in bits per byte (8 is the maximum)
+    pub entropy: f64,
+    // Chi-square of the range counts against a uniform split
+    pub chi_square: f64,
+    pub chi_square_p_value: f64,
+    // Wald-Wolfowitz runs test over the bitstream
+    pub runs_z_score: f64,
+    // Lag-1 autocorrelation of consecutive values
+    pub autocorrelation: f64,
+    pub mean_diff: f64,
+    pub variance_diff: f64,
+    pub repeats: u64,
+    pub repeat_percentage: f64,
+}
+
+// Why a sample failed the battery
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureClass {
+    // The source is broken: a bit never (or always) set, or values repeating
+    // wholesale. No amount of resampling will make this pass.
+    StuckSource,
+    // Some statistic landed outside its acceptance band: bias or bad luck
+    StatisticalWobble,
+    // Too few values (under 64) to tell a broken source from chance
+    InsufficientSample,
+}
+
+// Health check of a live generator (see SecureRng::quality_report): p-values of
+// the monobit, runs and lag-1 autocorrelation tests over a fresh sample
+#[derive(Clone, Debug)]
+pub struct QualityReport {
+    pub sample_size: usize,
+    pub monobit_p_value: f64,
+    pub runs_p_value: f64,
+    pub autocorrelation_p_value: f64,
+    // Every p-value at least 1e-4, the same cut-off the tester battery uses
+    pub passed: bool,
+}
+
+impl QualityReport {
+    pub fn from_sample(numbers: &[u32]) -> Self {
+        let monobit_p_value = monobit_test(numbers);
+        let runs_p_value = two_sided_p_value(runs_z_score(numbers));
+        // Under independence, r * sqrt(n) is approximately standard normal
+        let r = autocorrelation(numbers, 1);
+        let autocorrelation_p_value = two_sided_p_value(r * (numbers.len() as f64).sqrt());
+
+        QualityReport {
+            sample_size: numbers.len(),
+            monobit_p_value,
+            runs_p_value,
+            autocorrelation_p_value,
+            passed: [monobit_p_value, runs_p_value, autocorrelation_p_value]
+                .iter()
+                .all(|&p| p >= 1e-4),
+        }
+    }
+}
+
+impl DistributionStats {
+    pub fn from_sample(numbers: &[u32]) -> Self {
+        let total = numbers.len() as f64;
+
+        let mean = numbers.iter().map(|&x| x as f64).sum::<f64>() / total;
+        let variance = numbers.iter()
+            .map(|&x| {
+                let diff = x as f64 - mean;
+                diff * diff
+            })
+            .sum::<f64>() / total;
+
+        let mut range_counts = [0u64; 10];
+        let range_size = (u32::MAX as f64) / 10.0;
+        for &num in numbers {
+            let index = (num as f64 / range_size) as usize;
+            range_counts[index.min(9)] += 1;
+        }
+
+        let mut bit_counts = [0u64; 32];
+        for &num in numbers {
+            for (bit, count) in bit_counts.iter_mut().enumerate() {
+                if (num & (1 << bit)) != 0 {
+                    *count += 1;
+                }
+            }
+        }
+        let bit_frequencies = bit_counts.map(|count| (count as f64 / total) * 100.0);
+
+        let mut sum_diff = 0.0;
+        let mut sum_diff_squared = 0.0;
+        let mut repeats = 0;
+        for pair in numbers.windows(2) {
+            let diff = pair[1] as f64 - pair[0] as f64;
+            sum_diff += diff;
+            sum_diff_squared += diff * diff;
+            if pair[0] == pair[1] {
+                repeats += 1;
+            }
+        }
+        let len = numbers.len().saturating_sub(1) as f64;
+        let mean_diff = sum_diff / len;
+        let variance_diff = (sum_diff_squared / len) - (mean_diff * mean_diff);
+
+        let chi_square = chi_square_statistic(&range_counts);
+
+        DistributionStats {
+            sample_size: numbers.len(),
+            mean,
+            expected_mean: (u32::MAX as f64) / 2.0,
+            std_dev: variance.sqrt(),
+            range_counts,
+            bit_counts,
+            bit_frequencies,
+            entropy: byte_entropy(numbers),
+            chi_square,
+            chi_square_p_value: chi_square_p_value(chi_square, 9.0),
+            runs_z_score: runs_z_score(numbers),
+            autocorrelation: autocorrelation(numbers, 1),
+            mean_diff,
+            variance_diff,
+            repeats,
+            repeat_percentage: (repeats as f64 / total) * 100.0,
+        }
+    }
+
+    pub fn passes_all(&self) -> bool {
+        self.failure_class().is_none()
+    }
+
+    // None when every criterion passes; stuck-source checks take precedence.
+    // Samples too small to judge are never classified as stuck.
+    pub fn failure_class(&self) -> Option<FailureClass> {
+        if self.sample_size < MIN_JUDGED_SAMPLE {
+            return Some(FailureClass::InsufficientSample);
+        }
+
+        let stuck_bit = self.bit_counts
+            .iter()
+            .any(|&count| count == 0 || count == self.sample_size as u64);
+        let mostly_repeats = self.repeat_percentage >= 10.0;
+        if stuck_bit || mostly_repeats {
+            return Some(FailureClass::StuckSource);
+        }
+
+        if self.within_statistical_bounds() {
+            None
+        } else {
+            Some(FailureClass::StatisticalWobble)
+        }
+    }
+
+    // Every bit within 48-52%, almost no repeats, and no test beyond ~4 sigma.
+    // Written positively so that NaN (degenerate samples) counts as a failure.
+    fn within_statistical_bounds(&self) -> bool {
+        let bits_balanced = self.bit_frequencies.iter().all(|p| (48.0..=52.0).contains(p));
+        let few_repeats = self.repeat_percentage <= 0.1;
+        let uniform = self.chi_square_p_value >= 1e-4;
+        let runs_ok = self.runs_z_score.abs() < 4.0;
+        let uncorrelated =
+            self.autocorrelation.abs() < 4.0 / (self.sample_size as f64).sqrt();
+
+        bits_balanced && few_repeats && uniform && runs_ok && uncorrelated
+    }
+}
+
+// ASCII bar chart with one row per bin, labelled by bin index and count. The
+// largest bin spans `width` columns and the others are scaled to match.
+pub fn render_histogram(counts: &[u64], width: usize) -> String {
+    let largest = counts.iter().copied().max().unwrap_or(0);
+    let label_width = counts.len().saturating_sub(1).to_string().len();
+
+    let mut out = String::new();
+    for (bin, &count) in counts.iter().enumerate() {
+        let bar = if largest == 0 {
+            0
+        } else {
+            (count as f64 / largest as f64 * width as f64).round() as usize
+        };
+        out.push_str(&format!(
+            "{:>label_width$} | {:<width$} {}\n",
+            bin,
+            "#".repeat(bar),
+            count,
+        ));
+    }
+    out
+}
+
+// How counted quantities (range bins, bit frequencies, repeats) are printed.
+// Percentages can mislead on small samples, where raw counts are clearer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Percent,
+    RawCounts,
+    Both,
+}
+
+impl ReportFormat {
+    fn count(self, count: f64, total: f64, decimals: usize) -> String {
+        let percentage = (count / total) * 100.0;
+        match self {
+            ReportFormat::Percent => format!("{:.*}%", decimals, percentage),
+            ReportFormat::RawCounts => format!("{:.0}", count),
+            ReportFormat::Both => format!("{:.0} ({:.*}%)", count, decimals, percentage),
+        }
+    }
+}
+
+// The text report in a chosen format; see DistributionStats::with_format
+pub struct FormattedStats<'a> {
+    stats: &'a DistributionStats,
+    format: ReportFormat,
+}
+
+impl DistributionStats {
+    pub fn with_format(&self, format: ReportFormat) -> FormattedStats<'_> {
+        FormattedStats { stats: self, format }
+    }
+}
+
+impl fmt::Display for DistributionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with_format(ReportFormat::default()).fmt(f)
+    }
+}
+
+impl fmt::Display for FormattedStats<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stats = self.stats;
+        let format = self.format;
+        let total = stats.sample_size as f64;
+
+        writeln!(f, "=== Distribution Tests ===")?;
+        writeln!(f, "Mean: {:.2} (Expected: {:.2})", stats.mean, stats.expected_mean)?;
+        writeln!(f, "Standard Deviation: {:.2}", stats.std_dev)?;
+        writeln!(f, "\nDistribution across ranges:")?;
+        let expected = format.count(total / 10.0, total, 2);
+        for (i, &count) in stats.range_counts.iter().enumerate() {
+            let observed = format.count(count as f64, total, 2);
+            writeln!(f, "Range {}: {} (Expected: {})", i, observed, expected)?;
+        }
+        writeln!(f, "Chi-square: {:.2} (p-value: {:.4})", stats.chi_square, stats.chi_square_p_value)?;
+        writeln!(f)?;
+
+        writeln!(f, "=== Bit Pattern Analysis ===")?;
+        writeln!(f, "Bit distribution (should be close to 50% for each bit):")?;
+        for (bit, &count) in stats.bit_counts.iter().enumerate() {
+            writeln!(f, "Bit {}: {}", bit, format.count(count as f64, total, 2))?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "=== Entropy Analysis ===")?;
+        writeln!(f, "Byte entropy: {:.4} bits/byte", stats.entropy)?;
+        writeln!(f, "Maximum possible entropy: 8 bits/byte")?;
+        writeln!(f, "Entropy ratio: {:.2}%", (stats.entropy / 8.0) * 100.0)?;
+        writeln!(f)?;
+
+        writeln!(f, "=== Sequence Analysis ===")?;
+        writeln!(f, "Sequential difference analysis:")?;
+        writeln!(f, "Mean difference between consecutive numbers: {:.2}", stats.mean_diff)?;
+        writeln!(f, "Variance of differences: {:.2}", stats.variance_diff)?;
+        writeln!(f, "Runs test z-score: {:.4} (should be within +-4)", stats.runs_z_score)?;
+        writeln!(f, "Lag-1 autocorrelation: {:.6}", stats.autocorrelation)?;
+        writeln!(
+            f,
+            "Repeated numbers: {} (should be very close to 0%)",
+            format.count(stats.repeats as f64, total, 4)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SecureRng;
+
+    #[test]
+    fn test_battery_on_seeded_sample() {
+        let mut rng = SecureRng::from_seed([2u8; 32]);
+        let sample: Vec<u32> = (0..100_000).map(|_| rng.next_u32().unwrap()).collect();
+        let stats = DistributionStats::from_sample(&sample);
+
+        assert_eq!(stats.sample_size, 100_000);
+        assert_eq!(stats.range_counts.iter().sum::<u64>(), 100_000);
+        assert!(stats.bit_counts.iter().all(|&count| count > 0));
+        assert!((stats.mean / stats.expected_mean - 1.0).abs() < 0.01);
+        assert!(stats.std_dev > 0.0);
+        assert!(stats.entropy > 7.99);
+        assert!(stats.chi_square.is_finite() && stats.chi_square_p_value > 0.0);
+        assert!(stats.runs_z_score.is_finite());
+        assert!(stats.autocorrelation.is_finite());
+        assert!(stats.passes_all());
+
+        let text = stats.to_string();
+        assert!(text.contains("=== Bit Pattern Analysis ==="));
+        assert!(text.contains("Bit 31:"));
+    }
+
+    #[test]
+    fn test_quality_report_flags_patterns() {
+        let report = QualityReport::from_sample(&[0xAAAA_AAAA; 1000]);
+        assert!(report.runs_p_value < 1e-10);
+        assert!(report.autocorrelation_p_value.is_nan());
+        assert!(!report.passed);
+
+        let counting: Vec<u32> = (0..10_000u32).map(|i| i.wrapping_mul(0x9e37_79b9) >> 4).collect();
+        assert!(!QualityReport::from_sample(&counting).passed);
+    }
+
+    #[test]
+    fn test_report_formats() {
+        let mut rng = SecureRng::from_seed([4u8; 32]);
+        let sample: Vec<u32> = (0..1000).map(|_| rng.next_u32().unwrap()).collect();
+        let stats = DistributionStats::from_sample(&sample);
+
+        let percent = stats.with_format(ReportFormat::Percent).to_string();
+        assert_eq!(percent, stats.to_string());
+        assert!(percent.contains("(Expected: 10.00%)"));
+
+        let raw = stats.with_format(ReportFormat::RawCounts).to_string();
+        let range_line = format!("Range 0: {} (Expected: 100)", stats.range_counts[0]);
+        assert!(raw.contains(&range_line), "{}", raw);
+        let bit_line = format!("Bit 31: {}\n", stats.bit_counts[31]);
+        assert!(raw.contains(&bit_line));
+        let counted = |line: &&str| {
+            line.starts_with("Range ") || line.starts_with("Bit ") && !line.contains("should")
+        };
+        assert!(raw.lines().filter(counted).all(|line| !line.contains('%')));
+
+        let both = stats.with_format(ReportFormat::Both).to_string();
+        let both_line = format!(
+            "Bit 31: {} ({:.2}%)",
+            stats.bit_counts[31], stats.bit_frequencies[31]
+        );
+        assert!(both.contains(&both_line), "{}", both);
+        assert!(both.contains("(Expected: 100 (10.00%))"));
+    }
+
+    #[test]
+    fn test_render_histogram() {
+        let chart = render_histogram(&[5, 20, 10, 0], 40);
+        let rows: Vec<&str> = chart.lines().collect();
+        assert_eq!(rows.len(), 4);
+
+        let bar = |row: &str| row.matches('#').count();
+        assert_eq!(bar(rows[1]), 40);
+        assert_eq!(bar(rows[0]), 10);
+        assert_eq!(bar(rows[2]), 20);
+        assert_eq!(bar(rows[3]), 0);
+        assert!(rows[1].starts_with("1 | ") && rows[1].ends_with(" 20"));
+
+        assert_eq!(render_histogram(&[0, 0], 10).matches('#').count(), 0);
+        assert_eq!(render_histogram(&[], 10), "");
+    }
+
+    #[test]
+    fn test_battery_rejects_constant_sample() {
+        let stats = DistributionStats::from_sample(&[0xdead_beef; 1000]);
+        assert!(!stats.passes_all());
+        assert_eq!(stats.failure_class(), Some(FailureClass::StuckSource));
+    }
+
+    #[test]
+    fn test_small_sample_is_not_stuck() {
+        let mut rng = SecureRng::from_seed([0u8; 32]);
+        for n in [2, 10, MIN_JUDGED_SAMPLE - 1] {
+            let sample: Vec<u32> = (0..n).map(|_| rng.next_u32().unwrap()).collect();
+            let stats = DistributionStats::from_sample(&sample);
+            assert_eq!(stats.failure_class(), Some(FailureClass::InsufficientSample));
+        }
+
+        let sample: Vec<u32> = (0..MIN_JUDGED_SAMPLE).map(|_| rng.next_u32().unwrap()).collect();
+        let stats = DistributionStats::from_sample(&sample);
+        assert_ne!(stats.failure_class(), Some(FailureClass::StuckSource));
+        assert_ne!(stats.failure_class(), Some(FailureClass::InsufficientSample));
+    }
+
+    #[test]
+    fn test_failure_classes() {
+        let mut rng = SecureRng::from_seed([3u8; 32]);
+        let sample: Vec<u32> = (0..100_000).map(|_| rng.next_u32().unwrap()).collect();
+        assert_eq!(DistributionStats::from_sample(&sample).failure_class(), None);
+
+        // Bit 31 never set: broken, not merely biased
+        let halved: Vec<u32> = sample.iter().map(|&x| x >> 1).collect();
+        let stats = DistributionStats::from_sample(&halved);
+        assert_eq!(stats.failure_class(), Some(FailureClass::StuckSource));
+
+        // Bit 0 set 60% of the time: biased but alive
+        let biased: Vec<u32> = sample
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| if i % 5 == 0 { x | 1 } else { x })
+            .collect();
+        let stats = DistributionStats::from_sample(&biased);
+        assert_eq!(stats.failure_class(), Some(FailureClass::StatisticalWobble));
+    }
+}
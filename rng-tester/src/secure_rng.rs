@@ -0,0 +1,3377 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::num::NonZeroU32;
+use std::ops::{Range, RangeInclusive};
+use std::path::PathBuf;
+
+use crate::chacha::ChaCha20;
+use crate::fork;
+use crate::get_random_bytes;
+use crate::jitter::JitterEntropy;
+use crate::report::QualityReport;
+use crate::sha256::Sha256;
+use crate::stats::{ln_binomial_pmf, min_entropy_mcv};
+
+#[derive(Debug)]
+pub enum RngError {
+    // `context` names the step that failed, e.g. "opening /dev/urandom"
+    IoError { context: &'static str, source: io::Error },
+    EntropyError,
+    BufferTooLarge,
+    InvalidRange,
+    // A continuous sampler's result overflowed to infinity or became NaN
+    NonFinite,
+    // The internal buffer could not be allocated (see SecureRng::try_new)
+    AllocationFailed,
+    // The thread-local generator was used from inside its own refill
+    Reentrancy,
+}
+
+impl From<io::Error> for RngError {
+    fn from(error: io::Error) -> Self {
+        RngError::IoError { context: "I/O", source: error }
+    }
+}
+
+// Attach the failing step to an io::Error on its way into RngError
+pub(crate) trait IoContext<T> {
+    fn context(self, context: &'static str) -> Result<T, RngError>;
+}
+
+impl<T> IoContext<T> for io::Result<T> {
+    fn context(self, context: &'static str) -> Result<T, RngError> {
+        self.map_err(|source| RngError::IoError { context, source })
+    }
+}
+
+impl From<RngError> for io::Error {
+    fn from(error: RngError) -> Self {
+        match error {
+            RngError::IoError { context, source } => {
+                io::Error::new(source.kind(), format!("{}: {}", context, source))
+            }
+            other => io::Error::other(format!("{:?}", other)),
+        }
+    }
+}
+
+const BUFFER_SIZE: usize = 1024;
+// Largest single OS read when filling a caller's buffer directly
+const DIRECT_READ_LIMIT: usize = 1 << 30;
+// Smallest min_entropy_estimate sample: with fewer bytes than byte values the
+// MCV bound is too loose to say anything (2 bytes always estimate 0 bits)
+const MIN_ENTROPY_SAMPLE: usize = 256;
+
+// Entropy sources selectable through the builder
+#[derive(Clone, Debug)]
+pub enum EntropySource {
+    // /dev/urandom on Unix, CryptoAPI on Windows
+    Os,
+    // Timer-jitter entropy for targets with no usable OS generator. Much lower
+    // quality than the OS source: it must be selected explicitly, and building
+    // a generator with it prints a warning to stderr.
+    JitterEntropy,
+    // A custom entropy device such as /dev/hwrng, opened on first use
+    Device(PathBuf),
+    // Userspace ChaCha20 keyed from the OS: cryptographic quality without a
+    // syscall per refill. Keyed when the generator is built (try_build reports
+    // a failed OS read) and rekeyed every `reseed_interval` bytes.
+    ChaCha20Seeded,
+    // Caller-supplied entropy, e.g. a hardware RNG driver on a bare-metal
+    // target. Its quality is unknown; consider enabling `whiten`.
+    Callback(fn(&mut [u8]) -> io::Result<()>),
+}
+
+// Callbacks compare by address, which is best-effort: the same function can
+// have different addresses in different codegen units
+impl PartialEq for EntropySource {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (EntropySource::Os, EntropySource::Os) => true,
+            (EntropySource::JitterEntropy, EntropySource::JitterEntropy) => true,
+            (EntropySource::Device(a), EntropySource::Device(b)) => a == b,
+            (EntropySource::ChaCha20Seeded, EntropySource::ChaCha20Seeded) => true,
+            (EntropySource::Callback(a), EntropySource::Callback(b)) => std::ptr::fn_addr_eq(*a, *b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for EntropySource {}
+
+// Where refills of the internal buffer come from
+enum Backend {
+    Os,
+    Seeded(ChaCha20),
+    Jitter(JitterEntropy),
+    Device { path: PathBuf, file: Option<File> },
+    OsSeededChaCha { cipher: Option<ChaCha20>, since_reseed: usize },
+    Callback(fn(&mut [u8]) -> io::Result<()>),
+    Reader(Box<dyn Read + Send>),
+    // Fixed output preloaded into the buffer; nothing to refill from
+    Replay,
+}
+
+pub struct SecureRng {
+    buffer: Vec<u8>,
+    position: usize,
+    backend: Backend,
+    max_fill_bytes: usize,
+    reseed_interval: usize,
+    os_reads: u64,
+    bytes_consumed: u64,
+    // Fork generation that filled the buffer; a forked child must not replay it
+    fork_generation: u64,
+    fork_source: fn() -> u64,
+    // Second value of the last Box-Muller pair, as a standard normal
+    cached_normal: Option<f64>,
+    // Unused low bits of the last word drawn for next_bits
+    bit_cache: u64,
+    bit_count: u32,
+    // Rolls of a `die_sides`-sided die still packed into the last word drawn for gen_die
+    die_sides: u32,
+    die_cache: u64,
+    die_count: u32,
+    // Block counter for SHA-256 whitening; None when whitening is off
+    whiten_counter: Option<u64>,
+    // Keystream keyed by everything passed to fold_entropy, XORed into every
+    // refill after the first fold; None until then
+    fold_stream: Option<ChaCha20>,
+}
+
+// Snapshot of a generator's buffered output and cached values, see
+// SecureRng::checkpoint. Holds unread random bytes, so treat it like key material.
+#[derive(Clone)]
+pub struct Checkpoint {
+    buffer: Vec<u8>,
+    position: usize,
+    // Cipher state of a seeded generator, so its replay continues past the buffer
+    cipher: Option<ChaCha20>,
+    bytes_consumed: u64,
+    fork_generation: u64,
+    cached_normal: Option<f64>,
+    bit_cache: u64,
+    bit_count: u32,
+    die_sides: u32,
+    die_cache: u64,
+    die_count: u32,
+}
+
+pub struct SecureRngBuilder {
+    seed: Option<[u8; 32]>,
+    source: EntropySource,
+    max_fill_bytes: usize,
+    reseed_interval: usize,
+    whiten: bool,
+    fork_source: fn() -> u64,
+}
+
+impl SecureRngBuilder {
+    // Use the deterministic seeded stream instead of OS entropy; takes precedence over `source`
+    pub fn seed(mut self, seed: [u8; 32]) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn source(mut self, source: EntropySource) -> Self {
+        self.source = source;
+        self
+    }
+
+    // Bytes a ChaCha20Seeded generator serves before rekeying from the OS (default 1 MiB)
+    pub fn reseed_interval(mut self, bytes: usize) -> Self {
+        self.reseed_interval = bytes;
+        self
+    }
+
+    // Hash raw source output before serving it: each 64 raw bytes become 32
+    // bytes of SHA-256(counter || raw). This evens out a biased source but
+    // cannot add entropy the source does not have. Seeded generators ignore it.
+    pub fn whiten(mut self, whiten: bool) -> Self {
+        self.whiten = whiten;
+        self
+    }
+
+    // Stand-in for the fork generation counter so tests can simulate a fork
+    #[cfg(test)]
+    fn fork_source(mut self, fork_source: fn() -> u64) -> Self {
+        self.fork_source = fork_source;
+        self
+    }
+
+    // Largest single fill_bytes request accepted; unlimited by default
+    pub fn max_fill_bytes(mut self, limit: usize) -> Self {
+        self.max_fill_bytes = limit;
+        self
+    }
+
+    pub fn build(self) -> SecureRng {
+        let buffer = Vec::with_capacity(BUFFER_SIZE); // Preallocate buffer
+        let mut rng = self.build_with_buffer(buffer);
+        // No way to report a failure here: the first draw retries the key and returns the error
+        let _ = rng.key_os_cipher();
+        rng
+    }
+
+    // Like build, but reports a failed buffer allocation instead of aborting,
+    // and a failed OS read for the ChaCha20Seeded key instead of deferring it
+    // to the first draw
+    pub fn try_build(self) -> Result<SecureRng, RngError> {
+        let buffer = try_alloc_buffer(BUFFER_SIZE)?;
+        let mut rng = self.build_with_buffer(buffer);
+        rng.key_os_cipher()?;
+        Ok(rng)
+    }
+
+    fn build_with_buffer(self, buffer: Vec<u8>) -> SecureRng {
+        let backend = match (self.seed, self.source) {
+            (Some(seed), _) => Backend::Seeded(ChaCha20::new(seed)),
+            (None, EntropySource::Os) => Backend::Os,
+            (None, EntropySource::JitterEntropy) => {
+                eprintln!(
+                    "warning: SecureRng is using timer-jitter entropy; \
+                     output is NOT suitable for cryptographic use"
+                );
+                Backend::Jitter(JitterEntropy::new())
+            }
+            (None, EntropySource::Device(path)) => Backend::Device { path, file: None },
+            (None, EntropySource::ChaCha20Seeded) => Backend::OsSeededChaCha {
+                cipher: None,
+                since_reseed: 0,
+            },
+            (None, EntropySource::Callback(fill)) => Backend::Callback(fill),
+        };
+
+        SecureRng {
+            buffer,
+            position: 0,
+            backend,
+            max_fill_bytes: self.max_fill_bytes,
+            reseed_interval: self.reseed_interval,
+            os_reads: 0,
+            bytes_consumed: 0,
+            fork_generation: (self.fork_source)(),
+            fork_source: self.fork_source,
+            cached_normal: None,
+            bit_cache: 0,
+            bit_count: 0,
+            die_sides: 0,
+            die_cache: 0,
+            die_count: 0,
+            whiten_counter: self.whiten.then_some(0),
+            fold_stream: None,
+        }
+    }
+}
+
+impl Default for SecureRng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecureRng {
+    pub fn new() -> Self {
+        SecureRng::builder().build()
+    }
+
+    // For callers that must survive OOM: AllocationFailed instead of an abort
+    pub fn try_new() -> Result<Self, RngError> {
+        SecureRng::builder().try_build()
+    }
+
+    // Deterministic generator: the same seed always yields the same stream
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        SecureRng::builder().seed(seed).build()
+    }
+
+    // Generator whose next_u32 calls return `values` in order, then fail with
+    // EntropyError. For fuzzing code built on gen_range, shuffles and the like
+    // straight from a Vec<u32>: byte order is handled here, not by the caller.
+    pub fn from_u32_stream(values: Vec<u32>) -> Self {
+        SecureRng::from_fixed_bytes(values.iter().flat_map(|value| value.to_ne_bytes()).collect())
+    }
+
+    // Generator that serves exactly `bytes` and then fails with EntropyError.
+    // Multi-byte values are assembled as their methods document (next_u32 in
+    // native byte order, next_u32x4 little-endian, and so on).
+    pub fn from_fixed_bytes(bytes: Vec<u8>) -> Self {
+        let mut rng = SecureRng::builder().build();
+        rng.backend = Backend::Replay;
+        rng.buffer = bytes;
+        rng
+    }
+
+    // Generator that draws its raw bytes from `reader`: a file, a socket, a
+    // hardware device or, in tests, a Cursor. Whitening aside, output is the
+    // reader's bytes in order. Once the reader hits end of file, whatever it
+    // produced is served and the next refill fails with an UnexpectedEof IoError.
+    pub fn from_reader<R: Read + Send + 'static>(reader: R) -> Self {
+        let mut rng = SecureRng::builder().build();
+        rng.backend = Backend::Reader(Box::new(reader));
+        rng
+    }
+
+    // Run `f` with this generator serving exactly `bytes` (then EntropyError),
+    // as from_fixed_bytes would. The real source, its buffered output and any
+    // cached normal or bits are set aside and restored once `f` returns or
+    // unwinds, so a caught panic inside `f` doesn't leave the bytes injected.
+    pub fn with_fixed_bytes<R>(&mut self, bytes: &[u8], f: impl FnOnce(&mut SecureRng) -> R) -> R {
+        let guard = FixedBytesGuard {
+            backend: std::mem::replace(&mut self.backend, Backend::Replay),
+            buffer: std::mem::replace(&mut self.buffer, bytes.to_vec()),
+            position: std::mem::replace(&mut self.position, 0),
+            cached_normal: self.cached_normal.take(),
+            bit_cache: self.bit_cache,
+            bit_count: std::mem::replace(&mut self.bit_count, 0),
+            die_sides: self.die_sides,
+            die_cache: self.die_cache,
+            die_count: std::mem::replace(&mut self.die_count, 0),
+            rng: self,
+        };
+        f(guard.rng)
+    }
+
+    pub fn builder() -> SecureRngBuilder {
+        SecureRngBuilder {
+            seed: None,
+            source: EntropySource::Os,
+            max_fill_bytes: usize::MAX,
+            reseed_interval: 1024 * 1024,
+            whiten: false,
+            fork_source: fork::generation,
+        }
+    }
+
+    // Copy of a seeded generator that continues from the exact same position,
+    // so the copy and the parent produce identical output from here on. This is
+    // for speculatively drawing ahead and discarding, not for parallel work: a
+    // split would need an independent stream, which this deliberately is not.
+    // An OS-backed generator has no state to copy, so it gets a fresh one; a
+    // reader can't be duplicated, so a reader-backed copy draws from the OS.
+    // A replaying generator is copied along with its remaining values.
+    pub fn clone_stream(&self) -> SecureRng {
+        let backend = match &self.backend {
+            Backend::Seeded(chacha) => Backend::Seeded(chacha.clone()),
+            Backend::Replay => Backend::Replay,
+            _ => {
+                return SecureRng::builder()
+                    .source(self.source())
+                    .max_fill_bytes(self.max_fill_bytes)
+                    .reseed_interval(self.reseed_interval)
+                    .whiten(self.whiten_counter.is_some())
+                    .build()
+            }
+        };
+
+        SecureRng {
+            buffer: self.buffer.clone(),
+            position: self.position,
+            backend,
+            max_fill_bytes: self.max_fill_bytes,
+            reseed_interval: self.reseed_interval,
+            os_reads: 0,
+            bytes_consumed: self.bytes_consumed,
+            fork_generation: self.fork_generation,
+            fork_source: self.fork_source,
+            cached_normal: self.cached_normal,
+            bit_cache: self.bit_cache,
+            bit_count: self.bit_count,
+            die_sides: self.die_sides,
+            die_cache: self.die_cache,
+            die_count: self.die_count,
+            whiten_counter: self.whiten_counter,
+            fold_stream: None,
+        }
+    }
+
+    // Snapshot the buffered bytes and cached values so restore can replay the
+    // same draws later in this run, e.g. for a two-pass algorithm. For an
+    // OS-backed generator only the bytes buffered now (buffered_remaining;
+    // call reseed first for a full buffer) replay exactly: draws past them
+    // trigger a refill with fresh entropy. A seeded generator's cipher state is
+    // captured too, so its replay is exact however far it goes. A checkpoint
+    // holds output that may already have become keys or nonces: never persist
+    // it, send it elsewhere or restore it across a security boundary.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            buffer: self.buffer.clone(),
+            position: self.position,
+            cipher: match &self.backend {
+                Backend::Seeded(chacha) => Some(chacha.clone()),
+                _ => None,
+            },
+            bytes_consumed: self.bytes_consumed,
+            fork_generation: self.fork_generation,
+            cached_normal: self.cached_normal,
+            bit_cache: self.bit_cache,
+            bit_count: self.bit_count,
+            die_sides: self.die_sides,
+            die_cache: self.die_cache,
+            die_count: self.die_count,
+        }
+    }
+
+    // Rewind to `checkpoint`: the following draws repeat those made after it
+    // was taken. A checkpoint from another process (e.g. before a fork) is
+    // discarded on the next draw rather than replayed.
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        if let (Backend::Seeded(chacha), Some(cipher)) = (&mut self.backend, checkpoint.cipher) {
+            *chacha = cipher;
+        }
+        self.buffer = checkpoint.buffer;
+        self.position = checkpoint.position;
+        self.bytes_consumed = checkpoint.bytes_consumed;
+        self.fork_generation = checkpoint.fork_generation;
+        self.cached_normal = checkpoint.cached_normal;
+        self.bit_cache = checkpoint.bit_cache;
+        self.bit_count = checkpoint.bit_count;
+        self.die_sides = checkpoint.die_sides;
+        self.die_cache = checkpoint.die_cache;
+        self.die_count = checkpoint.die_count;
+    }
+
+    // Fold `seed` into a seeded generator's state at a checkpoint. Generators in
+    // the same state reseeded with the same bytes continue identically; different
+    // bytes diverge. Buffered output from the old state is discarded. OS-backed
+    // generators have no deterministic state, so this is a no-op for them.
+    pub fn reseed_from_slice(&mut self, seed: &[u8]) {
+        if let Backend::Seeded(chacha) = &mut self.backend {
+            chacha.reseed(seed);
+            self.position = self.buffer.len();
+            self.discard_cached_values();
+        }
+    }
+
+    // Mix application-supplied bytes (timings, input events) into the generator.
+    // Seeded generators fold them into the cipher key like reseed_from_slice,
+    // so the rest of the stream depends on them. Any other source keeps a
+    // ChaCha20 keystream keyed with everything folded so far and XORs it into
+    // the unread buffer now and into every refill after, OS rekeys included.
+    // XOR with an independent keystream keeps the output as unpredictable as
+    // the source entropy even when `extra` is attacker-controlled. Replayed
+    // fixed bytes are left untouched.
+    pub fn fold_entropy(&mut self, extra: &[u8]) -> Result<(), RngError> {
+        match self.backend {
+            Backend::Seeded(_) => {
+                self.reseed_from_slice(extra);
+                return Ok(());
+            }
+            Backend::Replay => return Ok(()),
+            _ => {}
+        }
+
+        let stream = match &mut self.fold_stream {
+            Some(stream) => {
+                stream.reseed(extra);
+                stream
+            }
+            None => {
+                let mut hasher = Sha256::new();
+                hasher.update(extra);
+                self.fold_stream.insert(ChaCha20::new(hasher.finalize()))
+            }
+        };
+        xor_keystream(stream, &mut self.buffer[self.position..]);
+        self.discard_cached_values();
+        Ok(())
+    }
+
+    // Key a ChaCha20Seeded generator from the OS; other backends have no key to draw
+    fn key_os_cipher(&mut self) -> Result<(), RngError> {
+        if let Backend::OsSeededChaCha { cipher, since_reseed } = &mut self.backend {
+            *cipher = Some(os_keyed_cipher()?);
+            *since_reseed = 0;
+            self.os_reads += 1;
+        }
+        Ok(())
+    }
+
+    // Discard all buffered output (including a cached normal) and refill from the source
+    pub fn reseed(&mut self) -> Result<(), RngError> {
+        self.discard_cached_values();
+        self.refill()
+    }
+
+    // Values derived from earlier output that are served without a new draw
+    fn discard_cached_values(&mut self) {
+        self.cached_normal = None;
+        self.bit_count = 0;
+        self.die_count = 0;
+    }
+
+    // Whether the next gen_normal will be served from the Box-Muller cache
+    pub fn has_cached_normal(&self) -> bool {
+        self.cached_normal.is_some()
+    }
+
+    // Continuous self-test for long-running services: draws `sample` fresh
+    // values from this generator and runs the monobit, runs and lag-1
+    // autocorrelation tests on them. The values drawn are not returned.
+    pub fn quality_report(&mut self, sample: usize) -> Result<QualityReport, RngError> {
+        if sample < 2 {
+            return Err(RngError::InvalidRange);
+        }
+
+        let mut numbers = Vec::with_capacity(sample);
+        for _ in 0..sample {
+            numbers.push(self.next_u32()?);
+        }
+        Ok(QualityReport::from_sample(&numbers))
+    }
+
+    // Min-entropy of `sample` fresh bytes in bits per byte (NIST SP 800-90B
+    // most-common-value estimator). Unlike Shannon entropy this bounds how well
+    // an attacker can guess each byte. The bytes drawn are not returned.
+    pub fn min_entropy_estimate(&mut self, sample: usize) -> Result<f64, RngError> {
+        if sample < MIN_ENTROPY_SAMPLE {
+            return Err(RngError::InvalidRange);
+        }
+
+        let mut bytes = vec![0u8; sample];
+        self.fill_bytes(&mut bytes)?;
+        Ok(min_entropy_mcv(&bytes))
+    }
+
+    // Bytes already drawn from the source but not yet handed out
+    pub fn buffered_remaining(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+
+    // Draws of `bytes_per_draw` bytes that the buffer can serve before the next
+    // refill from the source. Latency-sensitive callers can check this and call
+    // reseed while idle so the hot path never waits on a syscall.
+    pub fn estimated_draws_remaining(&self, bytes_per_draw: usize) -> usize {
+        match bytes_per_draw {
+            0 => usize::MAX,
+            n => self.buffered_remaining() / n,
+        }
+    }
+
+    // Number of times the buffer was refilled from the OS over this generator's lifetime
+    pub fn os_reads(&self) -> u64 {
+        self.os_reads
+    }
+
+    // Total random bytes handed to callers, however they were requested. For a
+    // seeded generator this is its position in the stream.
+    pub fn bytes_consumed(&self) -> u64 {
+        self.bytes_consumed
+    }
+
+    fn source(&self) -> EntropySource {
+        match &self.backend {
+            Backend::Jitter(_) => EntropySource::JitterEntropy,
+            Backend::Device { path, .. } => EntropySource::Device(path.clone()),
+            Backend::OsSeededChaCha { .. } => EntropySource::ChaCha20Seeded,
+            Backend::Callback(fill) => EntropySource::Callback(*fill),
+            _ => EntropySource::Os,
+        }
+    }
+
+    pub fn gen_range(&mut self, min: u32, max: u32) -> Result<u32, RngError> {
+        if min >= max {
+            return Err(RngError::InvalidRange);
+        }
+
+        self.gen_span(min, max - min)
+    }
+
+    // gen_range plus buffered_remaining afterwards, for snapshot tests that pin
+    // down how many bytes one call consumed (4 per draw, more on rejection).
+    // Debug builds only.
+    #[cfg(debug_assertions)]
+    pub fn gen_range_debug(&mut self, min: u32, max: u32) -> Result<(u32, usize), RngError> {
+        let value = self.gen_range(min, max)?;
+        Ok((value, self.buffered_remaining()))
+    }
+
+    // N values in [min, max) on the stack, with the range sampler (rejection
+    // threshold and fastmod reciprocal) set up once for the whole array
+    pub fn gen_range_n<const N: usize>(&mut self, min: u32, max: u32) -> Result<[u32; N], RngError> {
+        let sampler = self.range_sampler(min, max)?;
+        let mut values = [0u32; N];
+        for value in &mut values {
+            *value = sampler.sample(self)?;
+        }
+        Ok(values)
+    }
+
+    // Forgiving gen_range for bounds from untrusted input: never errors on
+    // ordering. Reversed bounds are swapped and an empty range yields `min`.
+    pub fn gen_range_clamped(&mut self, min: u32, max: u32) -> Result<u32, RngError> {
+        match min.cmp(&max) {
+            std::cmp::Ordering::Less => self.gen_range(min, max),
+            std::cmp::Ordering::Equal => Ok(min),
+            std::cmp::Ordering::Greater => self.gen_range(max, min),
+        }
+    }
+
+    // Uniform value in [0, bound)
+    pub fn gen_below(&mut self, bound: u32) -> Result<u32, RngError> {
+        self.gen_range(0, bound)
+    }
+
+    // Value in [min, max) from exactly one next_u64 and no rejection loop: the
+    // high 64 bits of word * span. Each result has floor or ceil of 2^64 / span
+    // preimages, so no value's probability is off by more than a factor of
+    // 1 + span / 2^64 (at most 1 + 2^-32). Not exactly uniform, but the bias
+    // is far below anything a statistical test could see.
+    pub fn gen_range_widening(&mut self, min: u32, max: u32) -> Result<u32, RngError> {
+        if min >= max {
+            return Err(RngError::InvalidRange);
+        }
+
+        let range = (max - min) as u128;
+        let offset = (self.next_u64()? as u128 * range) >> 64;
+        Ok(min + offset as u32)
+    }
+
+    // gen_range with the cheaper of two unbiased methods for this span. Classic
+    // rejection (gen_range) always divides twice: once for its threshold and
+    // once to reduce the draw. Lemire's multiply-shift divides only when the
+    // low half of the product lands below the span, with probability
+    // span / 2^32, but that branch is costly once it stops being predictable.
+    // Measured with bench_gen_range_auto_paths, Lemire is about 15% faster up
+    // to 2^28, level with classic around 2^29 to 2^30, and up to about 20%
+    // slower from there to 3 * 2^30, so small spans take Lemire and large
+    // spans take classic.
+    pub fn gen_range_auto(&mut self, min: u32, max: u32) -> Result<u32, RngError> {
+        if min >= max {
+            return Err(RngError::InvalidRange);
+        }
+
+        let range = max - min;
+        if uses_lemire(range) {
+            Ok(min + self.gen_below_lemire(range)?)
+        } else {
+            self.gen_range(min, max)
+        }
+    }
+
+    // Lemire's nearly divisionless method over [0, range); range must be non-zero
+    fn gen_below_lemire(&mut self, range: u32) -> Result<u32, RngError> {
+        let mut product = self.next_u32()? as u64 * range as u64;
+        if (product as u32) < range {
+            // Low halves below 2^32 mod range would over-represent some results
+            let threshold = range.wrapping_neg() % range;
+            while (product as u32) < threshold {
+                product = self.next_u32()? as u64 * range as u64;
+            }
+        }
+        Ok((product >> 32) as u32)
+    }
+
+    // gen_range as an f64 for plotting code. Still a discrete distribution: only
+    // the integers in [min, max) can appear, never the values between them.
+    pub fn gen_range_as_f64(&mut self, min: u32, max: u32) -> Result<f64, RngError> {
+        Ok(self.gen_range(min, max)? as f64)
+    }
+
+    // Reusable sampler for [min, max) with the rejection threshold precomputed
+    pub fn range_sampler(&self, min: u32, max: u32) -> Result<RangeSampler, RngError> {
+        if min >= max {
+            return Err(RngError::InvalidRange);
+        }
+
+        Ok(RangeSampler::with_span(min, max - min))
+    }
+
+    // Uniform value in [min, max], both ends included
+    pub fn gen_range_inclusive(&mut self, min: u32, max: u32) -> Result<u32, RngError> {
+        if min > max {
+            return Err(RngError::InvalidRange);
+        }
+
+        // The full u32 range has a span of 2^32, which does not fit in a u32
+        if min == 0 && max == u32::MAX {
+            return self.next_u32();
+        }
+
+        self.gen_span(min, max - min + 1)
+    }
+
+    // One-off draw from [min, min + range) with a plain modulo. A RangeSampler
+    // gives the same values, but its fastmod reciprocal costs a 64-bit divide
+    // that only pays off when the sampler is reused.
+    fn gen_span(&mut self, min: u32, range: u32) -> Result<u32, RngError> {
+        let threshold = rejection_threshold(range);
+        loop {
+            let value = self.next_u32()?;
+            if value <= threshold {
+                return Ok(min + value % range);
+            }
+        }
+    }
+
+    // Sample from `a..b` or `a..=b`, e.g. `rng.gen_in(1..=6)` for a die roll
+    pub fn gen_in<R: SampleRange>(&mut self, range: R) -> Result<u32, RngError> {
+        range.sample_from(self)
+    }
+
+    // The low `n` bits of the result are random (1 <= n <= 32). Bits come from
+    // a cached word, so many small requests cost one draw per 32 bits.
+    pub fn next_bits(&mut self, n: u32) -> Result<u32, RngError> {
+        if n == 0 || n > 32 {
+            return Err(RngError::InvalidRange);
+        }
+
+        self.discard_if_forked();
+        if self.bit_count < n {
+            self.bit_cache = self.next_u32()? as u64;
+            self.bit_count = 32;
+        }
+        let bits = self.bit_cache & ((1u64 << n) - 1);
+        self.bit_cache >>= n;
+        self.bit_count -= n;
+        Ok(bits as u32)
+    }
+
+    // Roll a fair die, returning a face in 1..=sides. Each u64 drawn is reduced
+    // to a uniform value below the largest power of `sides` that fits, whose
+    // base-`sides` digits serve the following rolls (24 of them for a d6).
+    // Rolling a different die discards the remaining digits.
+    pub fn gen_die(&mut self, sides: u32) -> Result<u32, RngError> {
+        if sides == 0 {
+            return Err(RngError::InvalidRange);
+        }
+        if sides == 1 {
+            return Ok(1);
+        }
+
+        self.discard_if_forked();
+        if self.die_count == 0 || self.die_sides != sides {
+            let (modulus, digits) = die_packing(sides);
+            // 2^64 mod modulus; words at or above 2^64 minus this would favour low values
+            let excess = (u64::MAX % modulus + 1) % modulus;
+            let word = loop {
+                let word = self.next_u64()?;
+                if word <= u64::MAX - excess {
+                    break word;
+                }
+            };
+            self.die_sides = sides;
+            self.die_cache = word % modulus;
+            self.die_count = digits;
+        }
+
+        let face = self.die_cache % sides as u64;
+        self.die_cache /= sides as u64;
+        self.die_count -= 1;
+        Ok(face as u32 + 1)
+    }
+
+    // +1 or -1 with equal probability, from a single cached bit
+    pub fn gen_sign(&mut self) -> Result<i32, RngError> {
+        Ok(if self.next_bits(1)? == 1 { 1 } else { -1 })
+    }
+
+    // Generate a random u32
+    pub fn next_u32(&mut self) -> Result<u32, RngError> {
+        Ok(u32::from_ne_bytes(self.take_array()?))
+    }
+
+    // Four u32s from one 16-byte buffer read, e.g. one per SIMD lane. Lanes
+    // are assembled little-endian so the output is the same on every target.
+    pub fn next_u32x4(&mut self) -> Result<[u32; 4], RngError> {
+        let bytes: [u8; 16] = self.take_array()?;
+        Ok(std::array::from_fn(|lane| {
+            u32::from_le_bytes(bytes[4 * lane..4 * lane + 4].try_into().unwrap())
+        }))
+    }
+
+    // Generate a random u64
+    pub fn next_u64(&mut self) -> Result<u64, RngError> {
+        Ok(u64::from_ne_bytes(self.take_array()?))
+    }
+
+    // Generate a random u128
+    pub fn next_u128(&mut self) -> Result<u128, RngError> {
+        Ok(u128::from_ne_bytes(self.take_array()?))
+    }
+
+    // Full-width bounded sampling over [min, max)
+    pub fn gen_range_u128(&mut self, min: u128, max: u128) -> Result<u128, RngError> {
+        if min >= max {
+            return Err(RngError::InvalidRange);
+        }
+
+        let range = max - min;
+        let zone = u128::MAX - (u128::MAX % range);
+        loop {
+            let value = self.next_u128()?;
+            if value < zone {
+                return Ok(min + (value % range));
+            }
+        }
+    }
+
+    // Signed [min, max). The span and offset live in u64 space, where even
+    // i64::MIN..i64::MAX fits, and the offset is added back with wrapping
+    // arithmetic: min + offset always lands in range, but as i64 math the
+    // intermediate `offset as i64` can be negative and `min + x` overflow.
+    pub fn gen_range_i64(&mut self, min: i64, max: i64) -> Result<i64, RngError> {
+        if min >= max {
+            return Err(RngError::InvalidRange);
+        }
+
+        let span = max.wrapping_sub(min) as u64;
+        let offset = self.gen_below_u64(span)?;
+        Ok(min.wrapping_add(offset as i64))
+    }
+
+    // Fixed-size read for word assembly: when the buffer holds N bytes this is a
+    // single word load, skipping the chunking loop of fill_bytes entirely
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], RngError> {
+        self.discard_if_forked();
+        if let Some(bytes) = self.buffer.get(self.position..self.position + N) {
+            let array: [u8; N] = bytes.try_into().unwrap();
+            self.position += N;
+            self.bytes_consumed += N as u64;
+            return Ok(array);
+        }
+
+        let mut array = [0u8; N];
+        self.fill_bytes(&mut array)?;
+        Ok(array)
+    }
+
+    pub fn fill_bytes(&mut self, buf: &mut [u8]) -> Result<(), RngError> {
+        self.copy_out(buf, false)
+    }
+
+    // fill_bytes for key material. With the `zeroize` feature, the internal
+    // buffer bytes handed out are overwritten straight away, so the generator
+    // does not keep a second copy of the key until its next refill. Without
+    // the feature this is exactly fill_bytes.
+    pub fn drain_into(&mut self, buf: &mut [u8]) -> Result<(), RngError> {
+        self.copy_out(buf, cfg!(feature = "zeroize"))
+    }
+
+    fn copy_out(&mut self, buf: &mut [u8], wipe: bool) -> Result<(), RngError> {
+        if buf.len() > self.max_fill_bytes {
+            return Err(RngError::BufferTooLarge);
+        }
+        self.discard_if_forked();
+
+        // Large requests are served in buffer-sized chunks
+        let mut filled = 0;
+        while filled < buf.len() {
+            if self.position == self.buffer.len() {
+                let direct = matches!(self.backend, Backend::Os)
+                    && self.whiten_counter.is_none()
+                    && self.fold_stream.is_none();
+                if buf.len() - filled >= BUFFER_SIZE && direct {
+                    self.fill_direct(&mut buf[filled..])?;
+                    break;
+                }
+                self.refill()?;
+            }
+
+            let n = (buf.len() - filled).min(self.buffer.len() - self.position);
+            let served = self.position..self.position + n;
+            buf[filled..filled + n].copy_from_slice(&self.buffer[served.clone()]);
+            if wipe {
+                self.buffer[served].fill(0);
+            }
+            self.position += n;
+            filled += n;
+        }
+        self.bytes_consumed += buf.len() as u64;
+        Ok(())
+    }
+
+    // Once the buffer is drained, a large OS-backed request is read straight
+    // into the caller's memory: one read per DIRECT_READ_LIMIT bytes instead of
+    // one per 1024, and no staging copy through the internal buffer
+    fn fill_direct(&mut self, buf: &mut [u8]) -> Result<(), RngError> {
+        for chunk in buf.chunks_mut(DIRECT_READ_LIMIT) {
+            get_random_bytes(chunk)?;
+            self.os_reads += 1;
+        }
+        Ok(())
+    }
+
+    // Extends `vec` with `n` fresh bytes; on error `vec` is left as it was
+    pub fn append_bytes(&mut self, vec: &mut Vec<u8>, n: usize) -> Result<(), RngError> {
+        let start = vec.len();
+        vec.reserve(n);
+        vec.resize(start + n, 0);
+        if let Err(e) = self.fill_bytes(&mut vec[start..]) {
+            vec.truncate(start);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // After fork() parent and child hold the same buffered bytes (and, for
+    // ChaCha20Seeded, the same key). A child sees the fork generation bumped by
+    // its atfork handler on the next draw and throws all of that away so the
+    // two processes diverge. This runs on every draw, so it is one atomic
+    // load rather than a getpid syscall. Seeded generators are deterministic
+    // by design and keep their stream.
+    fn discard_if_forked(&mut self) {
+        if matches!(self.backend, Backend::Seeded(_) | Backend::Replay) {
+            return;
+        }
+
+        let generation = (self.fork_source)();
+        if generation != self.fork_generation {
+            self.fork_generation = generation;
+            self.position = self.buffer.len();
+            self.discard_cached_values();
+            if let Backend::OsSeededChaCha { cipher, .. } = &mut self.backend {
+                *cipher = None;
+            }
+        }
+    }
+
+    fn refill(&mut self) -> Result<(), RngError> {
+        // An exhausted replay stays exhausted rather than serving zeros
+        if let Backend::Replay = self.backend {
+            return Err(RngError::EntropyError);
+        }
+
+        self.buffer.resize(BUFFER_SIZE, 0);
+        self.position = 0;
+        match &mut self.backend {
+            Backend::Os => {
+                get_random_bytes(&mut self.buffer)?;
+                self.os_reads += 1;
+            }
+            Backend::Seeded(chacha) => chacha.fill(&mut self.buffer),
+            Backend::Jitter(jitter) => jitter.fill(&mut self.buffer),
+            Backend::Device { path, file } => {
+                let file = match file {
+                    Some(file) => file,
+                    None => file.insert(File::open(&*path).context("opening entropy device")?),
+                };
+                read_fully(file, &mut self.buffer).context("reading entropy device")?;
+                self.os_reads += 1;
+            }
+            Backend::OsSeededChaCha { cipher, since_reseed } => {
+                // None after a fork, or if the key could not be drawn at build time
+                if cipher.is_none() || *since_reseed >= self.reseed_interval {
+                    *cipher = Some(os_keyed_cipher()?);
+                    self.os_reads += 1;
+                    *since_reseed = 0;
+                }
+                if let Some(cipher) = cipher {
+                    cipher.fill(&mut self.buffer);
+                }
+                *since_reseed += self.buffer.len();
+            }
+            Backend::Callback(fill) => fill(&mut self.buffer).context("entropy callback")?,
+            Backend::Reader(reader) => {
+                let filled = read_available(reader, &mut self.buffer).context("reading entropy reader")?;
+                self.buffer.truncate(filled);
+                self.os_reads += 1;
+            }
+            Backend::Replay => unreachable!("replay generators never refill"),
+        }
+
+        if let Some(counter) = &mut self.whiten_counter {
+            if !matches!(self.backend, Backend::Seeded(_)) {
+                whiten_in_place(&mut self.buffer, counter);
+            }
+        }
+        if let Some(stream) = &mut self.fold_stream {
+            xor_keystream(stream, &mut self.buffer);
+        }
+        Ok(())
+    }
+
+    // Uniform double in [0, 1) with 53 bits of precision
+    pub fn next_f64(&mut self) -> Result<f64, RngError> {
+        Ok(unit_f64(self.next_u64()?))
+    }
+
+    // Two independent doubles in [0, 1) from a single 16-byte buffer read
+    pub fn next_f64_pair(&mut self) -> Result<(f64, f64), RngError> {
+        let mut buf = [0u8; 16];
+        self.fill_bytes(&mut buf)?;
+        let (first, second) = buf.split_at(8);
+        let a = u64::from_ne_bytes(first.try_into().unwrap());
+        let b = u64::from_ne_bytes(second.try_into().unwrap());
+        Ok((unit_f64(a), unit_f64(b)))
+    }
+
+    // Uniform point in the unit square [0, 1) x [0, 1)
+    pub fn gen_unit_square(&mut self) -> Result<(f64, f64), RngError> {
+        self.next_f64_pair()
+    }
+
+    // Uniform point in the closed unit disk. Rejection from the enclosing
+    // square keeps it uniform; a naive (r, theta) polar draw would crowd points
+    // toward the centre. About 21% of candidate pairs are rejected.
+    pub fn gen_unit_disk(&mut self) -> Result<(f64, f64), RngError> {
+        loop {
+            let (u, v) = self.next_f64_pair()?;
+            let (x, y) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+            if x * x + y * y <= 1.0 {
+                return Ok((x, y));
+            }
+        }
+    }
+
+    // Random RGB color from a single buffer read
+    pub fn gen_rgb(&mut self) -> Result<[u8; 3], RngError> {
+        let mut color = [0u8; 3];
+        self.fill_bytes(&mut color)?;
+        Ok(color)
+    }
+
+    // Random RGBA color from a single buffer read
+    pub fn gen_rgba(&mut self) -> Result<[u8; 4], RngError> {
+        let mut color = [0u8; 4];
+        self.fill_bytes(&mut color)?;
+        Ok(color)
+    }
+
+    // Box-Muller on 53-bit uniforms; u1 comes from the open interval so ln(u1) is finite.
+    // Each transform yields two normals: the second is cached for the next call.
+    pub fn gen_normal(&mut self, mean: f64, std_dev: f64) -> Result<f64, RngError> {
+        if !(mean.is_finite() && std_dev.is_finite()) {
+            return Err(RngError::InvalidRange);
+        }
+        if let Some(z) = self.cached_normal.take() {
+            return finite(mean + std_dev * z);
+        }
+
+        let u1 = self.next_open_f64()?;
+        let u2 = self.next_f64()?;
+        let (z1, z2) = box_muller(u1, u2);
+        self.cached_normal = Some(z2);
+
+        finite(mean + std_dev * z1)
+    }
+
+    // Standard normal: mean 0, standard deviation 1
+    pub fn gen_standard_normal(&mut self) -> Result<f64, RngError> {
+        self.gen_normal(0.0, 1.0)
+    }
+
+    // Two standard normals with correlation `rho` in [-1, 1], for correlated
+    // processes such as paired Brownian increments. Uses one fresh Box-Muller
+    // pair and leaves gen_normal's cache alone.
+    pub fn gen_correlated_normals(&mut self, rho: f64) -> Result<(f64, f64), RngError> {
+        if !(-1.0..=1.0).contains(&rho) {
+            return Err(RngError::InvalidRange);
+        }
+
+        let u1 = self.next_open_f64()?;
+        let u2 = self.next_f64()?;
+        let (z1, z2) = box_muller(u1, u2);
+        Ok((z1, rho * z1 + (1.0 - rho * rho).sqrt() * z2))
+    }
+
+    // Uniform direction in 3D: a vector of independent standard normals is
+    // rotationally symmetric, so normalizing it lands uniformly on the sphere
+    pub fn gen_unit_vector_3d(&mut self) -> Result<[f64; 3], RngError> {
+        loop {
+            let v = [
+                self.gen_standard_normal()?,
+                self.gen_standard_normal()?,
+                self.gen_standard_normal()?,
+            ];
+            let norm = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+            // A (near-)zero vector has no direction to keep
+            if norm > 1e-150 {
+                return Ok(v.map(|x| x / norm));
+            }
+        }
+    }
+
+    // Zero-allocation batch of gen_normal: both values of each Box-Muller pair
+    // are written, and an odd tail leaves its partner cached as gen_normal would
+    pub fn fill_normal(&mut self, out: &mut [f64], mean: f64, std_dev: f64) -> Result<(), RngError> {
+        if !(mean.is_finite() && std_dev.is_finite()) {
+            return Err(RngError::InvalidRange);
+        }
+        if out.is_empty() {
+            return Ok(());
+        }
+        let start = match self.cached_normal.take() {
+            Some(z) => {
+                out[0] = finite(mean + std_dev * z)?;
+                1
+            }
+            None => 0,
+        };
+
+        let mut pairs = out[start..].chunks_exact_mut(2);
+        for pair in &mut pairs {
+            let u1 = self.next_open_f64()?;
+            let u2 = self.next_f64()?;
+            let (z1, z2) = box_muller(u1, u2);
+            pair[0] = finite(mean + std_dev * z1)?;
+            pair[1] = finite(mean + std_dev * z2)?;
+        }
+        if let [last] = pairs.into_remainder() {
+            *last = self.gen_normal(mean, std_dev)?;
+        }
+        Ok(())
+    }
+
+    // Exponential with the given rate (mean 1 / rate) via inverse transform
+    pub fn gen_exponential(&mut self, rate: f64) -> Result<f64, RngError> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(RngError::InvalidRange);
+        }
+
+        finite(-self.next_open_f64()?.ln() / rate)
+    }
+
+    // Gamma(shape, scale) via Marsaglia-Tsang; shape < 1 is boosted from shape + 1
+    pub fn gen_gamma(&mut self, shape: f64, scale: f64) -> Result<f64, RngError> {
+        // An infinite shape would never leave the rejection loop
+        if !(shape.is_finite() && shape > 0.0 && scale.is_finite() && scale > 0.0) {
+            return Err(RngError::InvalidRange);
+        }
+
+        if shape < 1.0 {
+            let boosted = self.gen_gamma(shape + 1.0, scale)?;
+            return finite(boosted * self.next_open_f64()?.powf(1.0 / shape));
+        }
+
+        let d = shape - 1.0 / 3.0;
+        let c = 1.0 / (9.0 * d).sqrt();
+        loop {
+            let x = self.gen_normal(0.0, 1.0)?;
+            let v = (1.0 + c * x).powi(3);
+            if v <= 0.0 {
+                continue;
+            }
+
+            let u = self.next_open_f64()?;
+            if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+                return finite(d * v * scale);
+            }
+        }
+    }
+
+    // Pareto(scale, shape) via inverse transform; every sample is >= scale
+    pub fn gen_pareto(&mut self, scale: f64, shape: f64) -> Result<f64, RngError> {
+        if !(scale.is_finite() && scale > 0.0 && shape.is_finite() && shape > 0.0) {
+            return Err(RngError::InvalidRange);
+        }
+
+        let u = self.next_open_f64()?;
+        finite(scale / (1.0 - u).powf(1.0 / shape))
+    }
+
+    // n sorted uniforms on [0, 1) in O(n), no sort: the partial sums of n + 1
+    // standard exponentials, divided by their total, are distributed exactly
+    // as the order statistics of n uniforms
+    pub fn gen_sorted_uniform(&mut self, n: usize) -> Result<Vec<f64>, RngError> {
+        let mut sums = Vec::with_capacity(n);
+        let mut total = 0.0;
+        for _ in 0..n {
+            total += -self.next_open_f64()?.ln();
+            sums.push(total);
+        }
+        total += -self.next_open_f64()?.ln();
+
+        for sum in &mut sums {
+            *sum /= total;
+        }
+        Ok(sums)
+    }
+
+    // Uniform point on the probability simplex (dim non-negative coordinates
+    // summing to 1) from the spacings of dim - 1 sorted uniforms. Equivalent
+    // to Dirichlet(1, ..., 1) without drawing any gamma variates.
+    pub fn gen_simplex_point(&mut self, dim: usize) -> Result<Vec<f64>, RngError> {
+        if dim == 0 {
+            return Err(RngError::InvalidRange);
+        }
+
+        let mut cuts = Vec::with_capacity(dim + 1);
+        cuts.push(0.0);
+        for _ in 1..dim {
+            cuts.push(self.next_f64()?);
+        }
+        cuts.sort_by(f64::total_cmp);
+        cuts.push(1.0);
+
+        Ok(cuts.windows(2).map(|pair| pair[1] - pair[0]).collect())
+    }
+
+    // Unbiased Fisher-Yates shuffle: position i swaps with a uniform index in
+    // 0..=i, so each of the n! orderings is equally likely
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) -> Result<(), RngError> {
+        for i in (1..slice.len()).rev() {
+            let j = self.gen_index(i + 1)?;
+            slice.swap(i, j);
+        }
+        Ok(())
+    }
+
+    // Fisher-Yates shuffle of `slice` that also returns the permutation applied:
+    // afterwards slice[i] holds what was at index perm[i]. Applying the same
+    // perm to a paired array keeps the two aligned.
+    pub fn permute_in_place<T>(&mut self, slice: &mut [T]) -> Result<Vec<usize>, RngError> {
+        let mut perm: Vec<usize> = (0..slice.len()).collect();
+        for i in (1..slice.len()).rev() {
+            let j = self.gen_index(i + 1)?;
+            slice.swap(i, j);
+            perm.swap(i, j);
+        }
+        Ok(perm)
+    }
+
+    // Boolean mask of length `total` with exactly `k` entries set, uniform over all such masks
+    pub fn gen_subset_mask(&mut self, total: usize, k: usize) -> Result<Vec<bool>, RngError> {
+        if k > total {
+            return Err(RngError::InvalidRange);
+        }
+
+        // Partial Fisher-Yates: the first k slots end up holding a uniform k-subset
+        let mut indices: Vec<usize> = (0..total).collect();
+        let mut mask = vec![false; total];
+        for i in 0..k {
+            let j = i + self.gen_index(total - i)?;
+            indices.swap(i, j);
+            mask[indices[i]] = true;
+        }
+        Ok(mask)
+    }
+
+    // Number of successes when drawing `draws` items without replacement from a
+    // population containing `successes` successes. Exact inverse-CDF sampling,
+    // walking outward from the mode so the pmf never underflows at the start.
+    pub fn gen_hypergeometric(
+        &mut self,
+        population: u64,
+        successes: u64,
+        draws: u64,
+    ) -> Result<u64, RngError> {
+        if successes > population || draws > population {
+            return Err(RngError::InvalidRange);
+        }
+
+        let failures = population - successes;
+        let lo = draws.saturating_sub(failures);
+        let hi = draws.min(successes);
+        if lo == hi {
+            return Ok(lo);
+        }
+
+        let (n, k, n_total) = (draws as f64, successes as f64, population as f64);
+        let mode = (((n + 1.0) * (k + 1.0) / (n_total + 2.0)).floor() as u64).clamp(lo, hi);
+        // C(K, m) C(N - K, n - m) / C(N, n) as a ratio of binomial pmfs with
+        // p = n / N: differencing ln-factorials of N cancels to noise once N
+        // passes ~1e13
+        let m = mode as f64;
+        let (p, q) = (n / n_total, (population - draws) as f64 / n_total);
+        let p_mode = (ln_binomial_pmf(m, k, p, q) + ln_binomial_pmf(n - m, failures as f64, p, q)
+            - ln_binomial_pmf(n, n_total, p, q))
+        .exp();
+
+        let mut u = unit_f64(self.next_u64()?) - p_mode;
+        if u < 0.0 {
+            return Ok(mode);
+        }
+
+        // N - K - n, exactly: subtracting the rounded floats loses it past 2^53
+        let offset = (failures as i128 - draws as i128) as f64;
+        let (mut down, mut p_down) = (mode, p_mode);
+        let (mut up, mut p_up) = (mode, p_mode);
+        while down > lo || up < hi {
+            if down > lo {
+                let d = down as f64;
+                p_down *= d * (offset + d) / ((k - d + 1.0) * (n - d + 1.0));
+                down -= 1;
+                u -= p_down;
+                if u < 0.0 {
+                    return Ok(down);
+                }
+            }
+            if up < hi {
+                let x = up as f64;
+                p_up *= (k - x) * (n - x) / ((x + 1.0) * (offset + x + 1.0));
+                up += 1;
+                u -= p_up;
+                if u < 0.0 {
+                    return Ok(up);
+                }
+            }
+        }
+
+        // Only reachable through floating-point rounding of the pmf total
+        Ok(mode)
+    }
+
+    // Index drawn from a precomputed cumulative distribution: `cdf` must be
+    // non-decreasing within [0, 1] and end at 1.0 (to within 1e-9). Index i
+    // is chosen with probability cdf[i] - cdf[i - 1].
+    pub fn sample_cdf(&mut self, cdf: &[f64]) -> Result<usize, RngError> {
+        let valid = cdf.windows(2).all(|pair| pair[0] <= pair[1])
+            && cdf.first().is_some_and(|&first| first >= 0.0)
+            && cdf.last().is_some_and(|&last| (last - 1.0).abs() <= 1e-9);
+        if !valid {
+            return Err(RngError::InvalidRange);
+        }
+
+        let u = self.next_f64()?;
+        let index = cdf.partition_point(|&c| c <= u);
+        // A total a hair under 1.0 can leave u past the last entry
+        Ok(index.min(cdf.len() - 1))
+    }
+
+    pub fn next_nonzero_u32(&mut self) -> Result<NonZeroU32, RngError> {
+        loop {
+            if let Some(nz) = NonZeroU32::new(self.next_u32()?) {
+                return Ok(nz);
+            }
+        }
+    }
+
+    // Unbiased index in [0, bound); bound must be non-zero
+    fn gen_index(&mut self, bound: usize) -> Result<usize, RngError> {
+        Ok(self.gen_below_u64(bound as u64)? as usize)
+    }
+
+    // Unbiased value in [0, bound); bound must be non-zero
+    fn gen_below_u64(&mut self, bound: u64) -> Result<u64, RngError> {
+        let zone = u64::MAX - (u64::MAX % bound);
+        loop {
+            let value = self.next_u64()?;
+            if value < zone {
+                return Ok(value % bound);
+            }
+        }
+    }
+
+    // Uniform double in the open interval (0, 1), built from 53 random bits
+    fn next_open_f64(&mut self) -> Result<f64, RngError> {
+        let bits = self.next_u64()? >> 11;
+        Ok((bits as f64 + 0.5) / (1u64 << 53) as f64)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RangeSampler {
+    min: u32,
+    range: u32,
+    // Largest accepted draw, from rejection_threshold
+    threshold: u32,
+    // ceil(2^64 / range), so that `value % range` needs no hardware divide
+    magic: u64,
+}
+
+impl RangeSampler {
+    fn with_span(min: u32, range: u32) -> Self {
+        RangeSampler {
+            min,
+            range,
+            threshold: rejection_threshold(range),
+            // Wraps to 0 for range 1, which still reduces everything to 0
+            magic: (u64::MAX / range as u64).wrapping_add(1),
+        }
+    }
+
+    // Lemire's fastmod: the low 64 bits of magic * value are the fractional
+    // part of value / range, and scaling that back up by range gives the
+    // remainder. Exact for every u32 value and divisor.
+    fn reduce(&self, value: u32) -> u32 {
+        let fraction = self.magic.wrapping_mul(value as u64);
+        ((fraction as u128 * self.range as u128) >> 64) as u32
+    }
+
+    pub fn sample(&self, rng: &mut SecureRng) -> Result<u32, RngError> {
+        loop {
+            let value = rng.next_u32()?;
+
+            if value > self.threshold {
+                continue;
+            }
+
+            return Ok(self.min + self.reduce(value));
+        }
+    }
+}
+
+// Largest u32 draw to accept when sampling [0, range) by `draw % range`. The
+// accepted draws 0..=threshold are the largest multiple of `range` that fits in
+// 2^32, so every remainder is hit equally often; anything above is redrawn.
+// 2^32 mod range is computed as (2^32 - range) mod range, i.e. in u32 as
+// range.wrapping_neg() % range. Panics if range is 0.
+pub fn rejection_threshold(range: u32) -> u32 {
+    u32::MAX - range.wrapping_neg() % range
+}
+
+// State set aside by SecureRng::with_fixed_bytes, put back when dropped
+struct FixedBytesGuard<'a> {
+    rng: &'a mut SecureRng,
+    backend: Backend,
+    buffer: Vec<u8>,
+    position: usize,
+    cached_normal: Option<f64>,
+    bit_cache: u64,
+    bit_count: u32,
+    die_sides: u32,
+    die_cache: u64,
+    die_count: u32,
+}
+
+impl Drop for FixedBytesGuard<'_> {
+    fn drop(&mut self) {
+        let rng = &mut *self.rng;
+        rng.backend = std::mem::replace(&mut self.backend, Backend::Replay);
+        rng.buffer = std::mem::take(&mut self.buffer);
+        rng.position = self.position;
+        rng.cached_normal = self.cached_normal;
+        rng.bit_cache = self.bit_cache;
+        rng.bit_count = self.bit_count;
+        rng.die_sides = self.die_sides;
+        rng.die_cache = self.die_cache;
+        rng.die_count = self.die_count;
+    }
+}
+
+// Ranges accepted by `SecureRng::gen_in`
+pub trait SampleRange {
+    fn sample_from(self, rng: &mut SecureRng) -> Result<u32, RngError>;
+}
+
+impl SampleRange for Range<u32> {
+    fn sample_from(self, rng: &mut SecureRng) -> Result<u32, RngError> {
+        if self.is_empty() {
+            return Err(RngError::InvalidRange);
+        }
+        rng.gen_range(self.start, self.end)
+    }
+}
+
+impl SampleRange for RangeInclusive<u32> {
+    fn sample_from(self, rng: &mut SecureRng) -> Result<u32, RngError> {
+        if self.is_empty() {
+            return Err(RngError::InvalidRange);
+        }
+        rng.gen_range_inclusive(*self.start(), *self.end())
+    }
+}
+
+// Fill `buf` completely from a reader that may return short reads (hardware
+// RNG devices often hand out a few bytes per call). Interrupted reads are
+// retried; an error or EOF is only reported if the retry makes no progress.
+fn read_fully<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<()> {
+    let mut filled = 0;
+    let mut stalled = false;
+
+    while filled < buf.len() {
+        let failure = match reader.read(&mut buf[filled..]) {
+            Ok(0) => io::Error::new(io::ErrorKind::UnexpectedEof, "entropy source returned no data"),
+            Ok(n) => {
+                filled += n;
+                stalled = false;
+                continue;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => e,
+        };
+
+        if stalled {
+            return Err(failure);
+        }
+        stalled = true;
+    }
+    Ok(())
+}
+
+// Like read_fully, but a reader that ends partway through `buf` is fine as
+// long as it produced something. Returns the number of bytes read.
+fn read_available<R: Read + ?Sized>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    if filled == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "entropy reader is exhausted"));
+    }
+    Ok(filled)
+}
+
+// Crossover for gen_range_auto, see there
+fn uses_lemire(range: u32) -> bool {
+    range < 1 << 30
+}
+
+// Largest power of `sides` representable in a u64, and its exponent
+fn die_packing(sides: u32) -> (u64, u32) {
+    let (mut modulus, mut digits) = (sides as u64, 1);
+    while let Some(next) = modulus.checked_mul(sides as u64) {
+        modulus = next;
+        digits += 1;
+    }
+    (modulus, digits)
+}
+
+// Replace each 64-byte block of raw input with SHA-256(counter || block),
+// halving the buffer. The counter keeps repeated raw blocks from repeating
+// in the output. A shorter final block (a reader that ended mid-block) is
+// hashed the same way and keeps half its length, rounded up.
+fn whiten_in_place(buffer: &mut Vec<u8>, counter: &mut u64) {
+    let mut whitened = 0;
+    for start in (0..buffer.len()).step_by(64) {
+        let block = start..buffer.len().min(start + 64);
+        let mut hasher = Sha256::new();
+        hasher.update(&counter.to_le_bytes());
+        hasher.update(&buffer[block.clone()]);
+        *counter = counter.wrapping_add(1);
+        // Each block's output lands before the next block's input, never over unread input
+        let len = block.len().div_ceil(2);
+        buffer[whitened..whitened + len].copy_from_slice(&hasher.finalize()[..len]);
+        whitened += len;
+    }
+    buffer.truncate(whitened);
+}
+
+fn os_keyed_cipher() -> Result<ChaCha20, RngError> {
+    let mut key = [0u8; 32];
+    get_random_bytes(&mut key)?;
+    Ok(ChaCha20::new(key))
+}
+
+// XOR the next bytes of `stream` into `buffer`
+fn xor_keystream(stream: &mut ChaCha20, buffer: &mut [u8]) {
+    let mut pad = [0u8; 64];
+    for chunk in buffer.chunks_mut(64) {
+        let pad = &mut pad[..chunk.len()];
+        stream.fill(pad);
+        chunk.iter_mut().zip(pad.iter()).for_each(|(b, p)| *b ^= p);
+    }
+}
+
+// Final check on continuous samplers: extreme parameters can overflow even
+// when every input is valid, and a NaN or infinity must not reach the caller
+fn finite(x: f64) -> Result<f64, RngError> {
+    if x.is_finite() {
+        Ok(x)
+    } else {
+        Err(RngError::NonFinite)
+    }
+}
+
+fn try_alloc_buffer(capacity: usize) -> Result<Vec<u8>, RngError> {
+    let mut buffer = Vec::new();
+    buffer.try_reserve_exact(capacity).map_err(|_| RngError::AllocationFailed)?;
+    Ok(buffer)
+}
+
+fn box_muller(u1: f64, u2: f64) -> (f64, f64) {
+    let radius = (-2.0 * u1.ln()).sqrt();
+    let (sin, cos) = (2.0 * std::f64::consts::PI * u2).sin_cos();
+    (radius * cos, radius * sin)
+}
+
+// Map the top 53 bits of a u64 onto [0, 1)
+fn unit_f64(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_streams_are_reproducible() {
+        let mut a = SecureRng::from_seed([7u8; 32]);
+        let mut b = SecureRng::from_seed([7u8; 32]);
+        let mut c = SecureRng::from_seed([8u8; 32]);
+
+        let xs: Vec<u32> = (0..1000).map(|_| a.next_u32().unwrap()).collect();
+        let ys: Vec<u32> = (0..1000).map(|_| b.next_u32().unwrap()).collect();
+        let zs: Vec<u32> = (0..1000).map(|_| c.next_u32().unwrap()).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+    }
+
+    #[test]
+    fn test_clone_stream_matches_parent() {
+        let mut parent = SecureRng::from_seed([3u8; 32]);
+        // Leave the parent part-way through its buffer
+        for _ in 0..100 {
+            parent.next_u32().unwrap();
+        }
+
+        let mut clone = parent.clone_stream();
+        let ahead: Vec<u32> = (0..1000).map(|_| clone.next_u32().unwrap()).collect();
+        let actual: Vec<u32> = (0..1000).map(|_| parent.next_u32().unwrap()).collect();
+        assert_eq!(ahead, actual);
+    }
+
+    #[test]
+    fn test_large_fill_with_raised_limit() {
+        let mut rng = SecureRng::builder().max_fill_bytes(8 * 1024 * 1024).build();
+        let mut buf = vec![0u8; 4 * 1024 * 1024];
+        rng.fill_bytes(&mut buf).unwrap();
+        assert!(buf[buf.len() - 1024..].iter().any(|&b| b != 0));
+
+        let mut capped = SecureRng::builder().max_fill_bytes(1024 * 1024).build();
+        assert!(matches!(
+            capped.fill_bytes(&mut buf),
+            Err(RngError::BufferTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_chunked_fill_matches_small_fills() {
+        let mut whole = SecureRng::from_seed([5u8; 32]);
+        let mut pieces = SecureRng::from_seed([5u8; 32]);
+
+        let mut big = vec![0u8; 5000];
+        whole.fill_bytes(&mut big).unwrap();
+
+        let mut small = Vec::with_capacity(5000);
+        for _ in 0..50 {
+            let mut chunk = [0u8; 100];
+            pieces.fill_bytes(&mut chunk).unwrap();
+            small.extend_from_slice(&chunk);
+        }
+        assert_eq!(big, small);
+    }
+
+    #[test]
+    fn test_subset_mask_has_k_bits_uniformly_placed() {
+        let mut rng = SecureRng::new();
+        assert!(rng.gen_subset_mask(3, 4).is_err());
+        assert_eq!(rng.gen_subset_mask(0, 0).unwrap(), Vec::<bool>::new());
+
+        let trials = 30_000;
+        let mut inclusion = [0u32; 10];
+        for _ in 0..trials {
+            let mask = rng.gen_subset_mask(10, 3).unwrap();
+            assert_eq!(mask.len(), 10);
+            assert_eq!(mask.iter().filter(|&&bit| bit).count(), 3);
+            for (count, &bit) in inclusion.iter_mut().zip(&mask) {
+                if bit {
+                    *count += 1;
+                }
+            }
+        }
+
+        // Each position should be chosen 3/10 of the time (9000 +- ~80)
+        for &count in &inclusion {
+            assert!((8600..9400).contains(&count), "inclusion count {}", count);
+        }
+    }
+
+    #[test]
+    fn test_f64_pair_in_range_and_uncorrelated() {
+        let mut rng = SecureRng::new();
+        let n = 100_000;
+        let (mut sum_a, mut sum_b, mut sum_ab, mut sum_aa, mut sum_bb) = (0.0, 0.0, 0.0, 0.0, 0.0);
+
+        for _ in 0..n {
+            let (a, b) = rng.next_f64_pair().unwrap();
+            assert!((0.0..1.0).contains(&a));
+            assert!((0.0..1.0).contains(&b));
+            sum_a += a;
+            sum_b += b;
+            sum_ab += a * b;
+            sum_aa += a * a;
+            sum_bb += b * b;
+        }
+
+        let n = n as f64;
+        let cov = sum_ab / n - (sum_a / n) * (sum_b / n);
+        let var_a = sum_aa / n - (sum_a / n).powi(2);
+        let var_b = sum_bb / n - (sum_b / n).powi(2);
+        let correlation = cov / (var_a * var_b).sqrt();
+        assert!(correlation.abs() < 0.02, "correlation {}", correlation);
+    }
+
+    #[test]
+    fn test_range_sampler_matches_gen_range() {
+        let mut direct = SecureRng::from_seed([9u8; 32]);
+        let mut sampled = SecureRng::from_seed([9u8; 32]);
+        let sampler = sampled.range_sampler(10, 17).unwrap();
+        assert!(direct.range_sampler(5, 5).is_err());
+
+        let mut direct_counts = [0u32; 7];
+        let mut sampler_counts = [0u32; 7];
+        for _ in 0..70_000 {
+            let a = direct.gen_range(10, 17).unwrap();
+            let b = sampler.sample(&mut sampled).unwrap();
+            assert_eq!(a, b);
+            direct_counts[(a - 10) as usize] += 1;
+            sampler_counts[(b - 10) as usize] += 1;
+        }
+
+        assert_eq!(direct_counts, sampler_counts);
+        for &count in &sampler_counts {
+            assert!((9_500..10_500).contains(&count), "bucket count {}", count);
+        }
+    }
+
+    #[test]
+    fn test_jitter_source_varies_between_calls() {
+        let mut rng = SecureRng::builder()
+            .source(EntropySource::JitterEntropy)
+            .build();
+
+        let draws: Vec<u64> = (0..200).map(|_| rng.next_u64().unwrap()).collect();
+        let mut distinct = draws.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), draws.len());
+
+        let mut other = SecureRng::builder()
+            .source(EntropySource::JitterEntropy)
+            .build();
+        assert_ne!(other.next_u64().unwrap(), draws[0]);
+    }
+
+    #[test]
+    fn test_reseed_from_slice_diverges_and_converges() {
+        let mut a = SecureRng::from_seed([4u8; 32]);
+        let mut b = SecureRng::from_seed([4u8; 32]);
+        let mut c = SecureRng::from_seed([4u8; 32]);
+        for rng in [&mut a, &mut b, &mut c] {
+            rng.next_u32().unwrap();
+        }
+
+        a.reseed_from_slice(b"checkpoint-1");
+        b.reseed_from_slice(b"checkpoint-1");
+        c.reseed_from_slice(b"checkpoint-2");
+
+        let xs: Vec<u32> = (0..500).map(|_| a.next_u32().unwrap()).collect();
+        let ys: Vec<u32> = (0..500).map(|_| b.next_u32().unwrap()).collect();
+        let zs: Vec<u32> = (0..500).map(|_| c.next_u32().unwrap()).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+
+        // Reseeding must change the stream relative to not reseeding at all
+        let mut plain = SecureRng::from_seed([4u8; 32]);
+        plain.next_u32().unwrap();
+        let ws: Vec<u32> = (0..500).map(|_| plain.next_u32().unwrap()).collect();
+        assert_ne!(xs, ws);
+    }
+
+    #[test]
+    fn test_buffering_reduces_os_reads() {
+        let mut rng = SecureRng::new();
+        assert_eq!(rng.os_reads(), 0);
+
+        for _ in 0..300 {
+            rng.next_u32().unwrap();
+        }
+        // 1200 bytes through a 1024-byte buffer
+        assert_eq!(rng.os_reads(), 2);
+
+        let mut seeded = SecureRng::from_seed([0u8; 32]);
+        seeded.next_u32().unwrap();
+        assert_eq!(seeded.os_reads(), 0);
+    }
+
+    #[test]
+    fn test_rgb_channels_vary_from_one_read() {
+        let mut rng = SecureRng::new();
+        rng.gen_rgb().unwrap();
+        rng.gen_rgba().unwrap();
+        assert_eq!(rng.os_reads(), 1);
+
+        let draws = 10_000;
+        let mut sums = [0u64; 3];
+        let mut gray = 0;
+        for _ in 0..draws {
+            let [r, g, b] = rng.gen_rgb().unwrap();
+            if r == g && g == b {
+                gray += 1;
+            }
+            sums[0] += r as u64;
+            sums[1] += g as u64;
+            sums[2] += b as u64;
+        }
+
+        // Equal channels should happen 1 in 65536 draws
+        assert!(gray < 5, "{} gray colors", gray);
+        for sum in sums {
+            let mean = sum as f64 / draws as f64;
+            assert!((120.0..135.0).contains(&mean), "channel mean {}", mean);
+        }
+    }
+
+    #[test]
+    fn test_gen_in_exclusive_range() {
+        let mut rng = SecureRng::new();
+        let mut seen = [false; 5];
+        for _ in 0..1000 {
+            let value = rng.gen_in(10..15).unwrap();
+            assert!((10..15).contains(&value));
+            seen[(value - 10) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+
+        assert!(matches!(rng.gen_in(7..7), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_in(Range { start: 5, end: 3 }), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_gen_in_inclusive_range() {
+        let mut rng = SecureRng::new();
+        let mut faces = [0u32; 6];
+        for _ in 0..6000 {
+            let roll = rng.gen_in(1..=6).unwrap();
+            assert!((1..=6).contains(&roll));
+            faces[(roll - 1) as usize] += 1;
+        }
+        assert!(faces.iter().all(|&count| count > 800));
+
+        assert_eq!(rng.gen_in(9..=9).unwrap(), 9);
+        assert_eq!(rng.gen_in(u32::MAX..=u32::MAX).unwrap(), u32::MAX);
+        rng.gen_in(0..=u32::MAX).unwrap();
+
+        assert!(matches!(rng.gen_in(RangeInclusive::new(6, 1)), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_hypergeometric_mean() {
+        let mut rng = SecureRng::new();
+        assert!(matches!(rng.gen_hypergeometric(10, 11, 5), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_hypergeometric(10, 5, 11), Err(RngError::InvalidRange)));
+        assert_eq!(rng.gen_hypergeometric(50, 20, 0).unwrap(), 0);
+        assert_eq!(rng.gen_hypergeometric(50, 50, 7).unwrap(), 7);
+        assert_eq!(rng.gen_hypergeometric(50, 45, 50).unwrap(), 45);
+
+        // N = 100, K = 30, n = 20: mean 6, standard error ~0.013 over 20k draws
+        let samples = 20_000;
+        let mut sum = 0;
+        for _ in 0..samples {
+            let x = rng.gen_hypergeometric(100, 30, 20).unwrap();
+            assert!(x <= 20);
+            sum += x;
+        }
+        let mean = sum as f64 / samples as f64;
+        assert!((mean - 6.0).abs() < 0.07, "mean {}", mean);
+
+        // Large population: mean 500_000, standard deviation ~500
+        let mut sum = 0;
+        for _ in 0..200 {
+            sum += rng.gen_hypergeometric(1_000_000_000_000, 500_000_000_000, 1_000_000).unwrap();
+        }
+        let mean = sum as f64 / 200.0;
+        assert!((mean - 500_000.0).abs() < 250.0, "mean {}", mean);
+    }
+
+    #[test]
+    fn test_hypergeometric_huge_population() {
+        let mut rng = SecureRng::from_seed([19u8; 32]);
+        let mut moments = |population: u64, successes: u64, draws: u64| {
+            let samples = 4000;
+            let values: Vec<f64> = (0..samples)
+                .map(|_| rng.gen_hypergeometric(population, successes, draws).unwrap() as f64)
+                .collect();
+            let mean = values.iter().sum::<f64>() / samples as f64;
+            let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (samples - 1) as f64;
+            (mean, var)
+        };
+
+        // n = 1000 from K = N / 2: mean 500, variance ~250 (standard error ~6)
+        for population in [100_000_000_000_000, 1_000_000_000_000_000, 10_000_000_000_000_000, u64::MAX] {
+            let (mean, var) = moments(population, population / 2, 1000);
+            assert!((mean - 500.0).abs() < 2.0, "N {} mean {}", population, mean);
+            assert!((var - 250.0).abs() < 30.0, "N {} variance {}", population, var);
+        }
+
+        // Three successes, half the population drawn: Binomial(3, 1/2)
+        let (mean, var) = moments(1 << 62, 3, 1 << 61);
+        assert!((mean - 1.5).abs() < 0.05, "mean {}", mean);
+        assert!((var - 0.75).abs() < 0.05, "variance {}", var);
+    }
+
+    #[test]
+    fn test_aligned_and_unaligned_fills_match() {
+        let mut aligned_rng = SecureRng::from_seed([6u8; 32]);
+        let mut unaligned_rng = SecureRng::from_seed([6u8; 32]);
+
+        let mut first = vec![0u8; 4096 + 16];
+        let mut second = vec![0u8; 4096 + 16];
+        let offset = first.as_ptr().align_offset(8);
+        let aligned = &mut first[offset..offset + 4096];
+        let offset = second.as_ptr().align_offset(8) + 1;
+        let unaligned = &mut second[offset..offset + 4096];
+
+        aligned_rng.fill_bytes(aligned).unwrap();
+        unaligned_rng.fill_bytes(unaligned).unwrap();
+        assert_eq!(aligned, unaligned);
+
+        // Word reads straddling a refill boundary agree with byte-level reads
+        let mut word_rng = SecureRng::from_seed([6u8; 32]);
+        let mut byte_rng = SecureRng::from_seed([6u8; 32]);
+        word_rng.fill_bytes(&mut [0u8; 1021]).unwrap();
+        byte_rng.fill_bytes(&mut [0u8; 1021]).unwrap();
+        let mut expected = [0u8; 8];
+        byte_rng.fill_bytes(&mut expected).unwrap();
+        assert_eq!(word_rng.next_u64().unwrap(), u64::from_ne_bytes(expected));
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with --ignored --nocapture"]
+    fn bench_os_next_u32() {
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        let iterations = 10_000_000;
+        let mut rng = SecureRng::new();
+        let start = Instant::now();
+        for _ in 0..iterations {
+            black_box(rng.next_u32().unwrap());
+        }
+        let elapsed = start.elapsed();
+
+        println!(
+            "OS-backed next_u32: {:.2} ns/op ({} refills)",
+            elapsed.as_nanos() as f64 / iterations as f64,
+            rng.os_reads()
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with --ignored --nocapture"]
+    fn bench_word_reads() {
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        let iterations = 10_000_000;
+        let mut rng = SecureRng::from_seed([0u8; 32]);
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            black_box(rng.next_u64().unwrap());
+        }
+        let word_path = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            let mut buf = [0u8; 8];
+            rng.fill_bytes(&mut buf).unwrap();
+            black_box(u64::from_ne_bytes(buf));
+        }
+        let byte_path = start.elapsed();
+
+        println!(
+            "next_u64: {:.2} ns/op, fill_bytes(8): {:.2} ns/op",
+            word_path.as_nanos() as f64 / iterations as f64,
+            byte_path.as_nanos() as f64 / iterations as f64
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[ignore = "benchmark; run with --ignored --nocapture"]
+    fn bench_large_fill() {
+        use std::time::Instant;
+
+        let mut buf = vec![0u8; 64 * 1024 * 1024];
+        let mut direct = SecureRng::new();
+        let start = Instant::now();
+        direct.fill_bytes(&mut buf).unwrap();
+        let direct_time = start.elapsed();
+
+        // The same device read through the 1024-byte buffer
+        let mut chunked = SecureRng::builder()
+            .source(EntropySource::Device(PathBuf::from("/dev/urandom")))
+            .build();
+        let start = Instant::now();
+        chunked.fill_bytes(&mut buf).unwrap();
+        let chunked_time = start.elapsed();
+
+        println!(
+            "64 MiB fill: direct {:?} ({} reads), buffered {:?} ({} reads)",
+            direct_time,
+            direct.os_reads(),
+            chunked_time,
+            chunked.os_reads()
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with --ignored --nocapture"]
+    fn bench_gen_range_one_shot() {
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        let iterations = 10_000_000;
+        let mut rng = SecureRng::from_seed([0u8; 32]);
+
+        let start = Instant::now();
+        for i in 0..iterations {
+            black_box(rng.gen_range(0, black_box(1000 + (i & 7))).unwrap());
+        }
+        let one_shot = start.elapsed();
+
+        let sampler = rng.range_sampler(0, 1000).unwrap();
+        let start = Instant::now();
+        for _ in 0..iterations {
+            black_box(sampler.sample(&mut rng).unwrap());
+        }
+        let reused = start.elapsed();
+
+        println!(
+            "gen_range {:.2} ns, reused RangeSampler {:.2} ns",
+            one_shot.as_nanos() as f64 / iterations as f64,
+            reused.as_nanos() as f64 / iterations as f64
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with --ignored --nocapture"]
+    fn bench_gen_range_auto_paths() {
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        let iterations = 5_000_000;
+        let mut rng = SecureRng::from_seed([0u8; 32]);
+        let ranges = [
+            6u32, 1000, 1 << 20, 1 << 28, 1 << 29, 3 << 28, 1 << 30, (1 << 30) + 1, 3 << 29,
+            (1 << 31) - 1, 1 << 31, (1 << 31) + 1, 3 << 30, u32::MAX - 1,
+        ];
+        for range in ranges {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                black_box(rng.gen_below_lemire(black_box(range)).unwrap());
+            }
+            let lemire = start.elapsed();
+
+            let start = Instant::now();
+            for _ in 0..iterations {
+                black_box(rng.gen_range(0, black_box(range)).unwrap());
+            }
+            let classic = start.elapsed();
+
+            println!(
+                "range {:>10}: Lemire {:.2} ns, classic {:.2} ns",
+                range,
+                lemire.as_nanos() as f64 / iterations as f64,
+                classic.as_nanos() as f64 / iterations as f64
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with --ignored --nocapture"]
+    fn bench_gen_range_n() {
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        let iterations = 1_000_000;
+        let mut rng = SecureRng::from_seed([0u8; 32]);
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            black_box(rng.gen_range_n::<16>(0, 1000).unwrap());
+        }
+        let batched = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            let mut values = [0u32; 16];
+            for value in &mut values {
+                *value = rng.gen_range(0, 1000).unwrap();
+            }
+            black_box(values);
+        }
+        let separate = start.elapsed();
+
+        println!(
+            "16 values in [0, 1000): gen_range_n {:.2} ns, separate gen_range {:.2} ns",
+            batched.as_nanos() as f64 / iterations as f64,
+            separate.as_nanos() as f64 / iterations as f64
+        );
+    }
+
+    #[test]
+    fn test_gen_range_u128_spans_wide_range() {
+        let mut rng = SecureRng::new();
+        assert!(matches!(rng.gen_range_u128(5, 5), Err(RngError::InvalidRange)));
+
+        let max = 1u128 << 100;
+        let mut lowest = u128::MAX;
+        let mut highest = 0;
+        for _ in 0..10_000 {
+            let value = rng.gen_range_u128(0, max).unwrap();
+            assert!(value < max);
+            lowest = lowest.min(value);
+            highest = highest.max(value);
+        }
+        assert!(highest > max / 100 * 99, "highest {}", highest);
+        assert!(lowest < max / 100, "lowest {}", lowest);
+
+        let min = u128::MAX - 10;
+        for _ in 0..1000 {
+            let value = rng.gen_range_u128(min, u128::MAX).unwrap();
+            assert!((min..u128::MAX).contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_normal_uses_53_bit_uniforms() {
+        // Most extreme |z| a 32-bit u1 could produce
+        let tail_32 = box_muller(1.0 / u32::MAX as f64, 0.0).0;
+        assert!(tail_32 < 6.7);
+
+        // All-zero words give the smallest 53-bit u1, 2^-54, and u2 = 0. Only a
+        // 53-bit uniform reaches the resulting |z| of about 8.65.
+        let mut rng = SecureRng::from_fixed_bytes(vec![0u8; 16]);
+        let z = rng.gen_normal(0.0, 1.0).unwrap();
+        assert!(z > 8.6 && z > tail_32, "z = {}", z);
+        // One full u64 per uniform; the pair's second value comes from the cache
+        assert_eq!(rng.bytes_consumed(), 16);
+        assert!(rng.gen_normal(0.0, 1.0).unwrap().abs() < 1e-12);
+        assert_eq!(rng.bytes_consumed(), 16);
+
+        let mut rng = SecureRng::from_seed([13u8; 32]);
+        let n = 100_000;
+        let samples: Vec<f64> = (0..n).map(|_| rng.gen_normal(3.0, 2.0).unwrap()).collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+        assert!((mean - 3.0).abs() < 0.03, "mean {}", mean);
+        assert!((var - 4.0).abs() < 0.1, "variance {}", var);
+    }
+
+    #[test]
+    fn test_non_finite_parameters_are_rejected() {
+        let mut rng = SecureRng::from_seed([14u8; 32]);
+        for bad in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            assert!(matches!(rng.gen_exponential(bad), Err(RngError::InvalidRange)));
+            assert!(matches!(rng.gen_gamma(bad, 1.0), Err(RngError::InvalidRange)));
+            assert!(matches!(rng.gen_gamma(1.0, bad), Err(RngError::InvalidRange)));
+            assert!(matches!(rng.gen_normal(bad, 1.0), Err(RngError::InvalidRange)));
+            assert!(matches!(rng.gen_normal(0.0, bad), Err(RngError::InvalidRange)));
+            let mut out = [0.0; 3];
+            assert!(matches!(rng.fill_normal(&mut out, 0.0, bad), Err(RngError::InvalidRange)));
+        }
+
+        // Rejected before the cached partner is spent
+        rng.gen_normal(0.0, 1.0).unwrap();
+        assert!(rng.gen_normal(0.0, f64::INFINITY).is_err());
+        assert!(rng.cached_normal.is_some());
+        assert_eq!(rng.bytes_consumed(), 16);
+    }
+
+    #[test]
+    fn test_exponential_and_gamma_moments() {
+        let mut rng = SecureRng::from_seed([14u8; 32]);
+        assert!(matches!(rng.gen_exponential(0.0), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_gamma(0.0, 1.0), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_gamma(1.0, -1.0), Err(RngError::InvalidRange)));
+
+        let moments = |samples: &[f64]| {
+            let n = samples.len() as f64;
+            let mean = samples.iter().sum::<f64>() / n;
+            let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+            (mean, var)
+        };
+
+        let exp: Vec<f64> = (0..100_000).map(|_| rng.gen_exponential(2.0).unwrap()).collect();
+        let (mean, var) = moments(&exp);
+        assert!((mean - 0.5).abs() < 0.01, "exponential mean {}", mean);
+        assert!((var - 0.25).abs() < 0.01, "exponential variance {}", var);
+
+        let gamma: Vec<f64> = (0..100_000).map(|_| rng.gen_gamma(2.5, 2.0).unwrap()).collect();
+        let (mean, var) = moments(&gamma);
+        assert!((mean - 5.0).abs() < 0.05, "gamma mean {}", mean);
+        assert!((var - 10.0).abs() < 0.3, "gamma variance {}", var);
+
+        let small: Vec<f64> = (0..100_000).map(|_| rng.gen_gamma(0.5, 1.0).unwrap()).collect();
+        let (mean, var) = moments(&small);
+        assert!(small.iter().all(|&x| x > 0.0));
+        assert!((mean - 0.5).abs() < 0.02, "gamma(0.5) mean {}", mean);
+        assert!((var - 0.5).abs() < 0.03, "gamma(0.5) variance {}", var);
+    }
+
+    // Hands out at most `chunk` bytes per read, then fails once `available` runs out
+    struct TrickleReader {
+        chunk: usize,
+        available: usize,
+        next: u8,
+    }
+
+    impl Read for TrickleReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.available == 0 {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "device drained"));
+            }
+            let n = buf.len().min(self.chunk).min(self.available);
+            for byte in &mut buf[..n] {
+                *byte = self.next;
+                self.next = self.next.wrapping_add(1);
+            }
+            self.available -= n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_read_fully_accumulates_partial_reads() {
+        let mut reader = TrickleReader { chunk: 7, available: usize::MAX, next: 1 };
+        let mut buf = [0u8; 1024];
+        read_fully(&mut reader, &mut buf).unwrap();
+        for (i, &byte) in buf.iter().enumerate() {
+            assert_eq!(byte, (i + 1) as u8);
+        }
+
+        let mut drained = TrickleReader { chunk: 7, available: 20, next: 0 };
+        let err = read_fully(&mut drained, &mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_device_source() {
+        let mut rng = SecureRng::builder()
+            .source(EntropySource::Device(PathBuf::from("/dev/urandom")))
+            .build();
+        let values: Vec<u64> = (0..300).map(|_| rng.next_u64().unwrap()).collect();
+        assert!(values.windows(2).any(|w| w[0] != w[1]));
+        assert_eq!(rng.os_reads(), 3);
+
+        let mut missing = SecureRng::builder()
+            .source(EntropySource::Device(PathBuf::from("/nonexistent/hwrng")))
+            .build();
+        assert!(matches!(
+            missing.next_u32(),
+            Err(RngError::IoError { context: "opening entropy device", .. })
+        ));
+    }
+
+    #[test]
+    fn test_normal_cache_and_reseed() {
+        let mut rng = SecureRng::new();
+        assert!(!rng.has_cached_normal());
+
+        rng.gen_normal(0.0, 1.0).unwrap();
+        assert!(rng.has_cached_normal());
+        rng.gen_normal(0.0, 1.0).unwrap();
+        assert!(!rng.has_cached_normal());
+
+        rng.gen_normal(0.0, 1.0).unwrap();
+        assert!(rng.has_cached_normal());
+        rng.reseed().unwrap();
+        assert!(!rng.has_cached_normal());
+
+        let mut seeded = SecureRng::from_seed([17u8; 32]);
+        seeded.gen_normal(0.0, 1.0).unwrap();
+        seeded.reseed_from_slice(b"checkpoint");
+        assert!(!seeded.has_cached_normal());
+    }
+
+    #[test]
+    fn test_chacha20_seeded_source() {
+        let build = || {
+            SecureRng::builder()
+                .source(EntropySource::ChaCha20Seeded)
+                .reseed_interval(2048)
+                .build()
+        };
+        let mut rng = build();
+        let sample: Vec<u32> = (0..25_000).map(|_| rng.next_u32().unwrap()).collect();
+        let p = crate::stats::monobit_test(&sample);
+        assert!(p > 1e-4, "monobit p-value {}", p);
+
+        // 100_000 bytes through 1024-byte refills, rekeyed every second refill
+        assert_eq!(rng.os_reads(), 49);
+
+        let mut other = build();
+        let other_sample: Vec<u32> = (0..64).map(|_| other.next_u32().unwrap()).collect();
+        assert_ne!(sample[..64], other_sample[..]);
+
+        // The key is drawn while building, so try_build is where an OS failure shows
+        let keyed = SecureRng::builder()
+            .source(EntropySource::ChaCha20Seeded)
+            .try_build()
+            .unwrap();
+        assert_eq!(keyed.os_reads(), 1);
+        assert!(matches!(keyed.backend, Backend::OsSeededChaCha { cipher: Some(_), .. }));
+    }
+
+    #[test]
+    fn test_gen_range_clamped() {
+        let mut rng = SecureRng::from_seed([19u8; 32]);
+        for _ in 0..1000 {
+            let value = rng.gen_range_clamped(50, 10).unwrap();
+            assert!((10..50).contains(&value));
+        }
+        assert_eq!(rng.gen_range_clamped(7, 7).unwrap(), 7);
+        assert!(rng.gen_range(50, 10).is_err());
+    }
+
+    #[test]
+    fn test_append_bytes() {
+        let mut rng = SecureRng::from_seed([20u8; 32]);
+        let mut payload = Vec::new();
+        rng.append_bytes(&mut payload, 100).unwrap();
+        rng.append_bytes(&mut payload, 100).unwrap();
+        assert_eq!(payload.len(), 200);
+        assert_ne!(payload[..100], payload[100..]);
+
+        let mut limited = SecureRng::builder().seed([20u8; 32]).max_fill_bytes(64).build();
+        assert!(matches!(
+            limited.append_bytes(&mut payload, 65),
+            Err(RngError::BufferTooLarge)
+        ));
+        assert_eq!(payload.len(), 200);
+    }
+
+    #[test]
+    fn test_fill_normal() {
+        let mut rng = SecureRng::from_seed([21u8; 32]);
+        let mut out = [f64::NAN; 1001];
+        rng.fill_normal(&mut out, 5.0, 2.0).unwrap();
+        assert!(out.iter().all(|x| x.is_finite()));
+        assert!(rng.has_cached_normal());
+
+        let mut out = vec![f64::NAN; 100_001];
+        rng.fill_normal(&mut out, 5.0, 2.0).unwrap();
+        assert!(out.iter().all(|x| x.is_finite()));
+        assert!(!rng.has_cached_normal());
+        let n = out.len() as f64;
+        let mean = out.iter().sum::<f64>() / n;
+        let var = out.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        assert!((mean - 5.0).abs() < 0.03, "mean {}", mean);
+        assert!((var.sqrt() - 2.0).abs() < 0.03, "std dev {}", var.sqrt());
+
+        // Same stream as drawing one value at a time
+        let mut batched = SecureRng::from_seed([22u8; 32]);
+        let mut single = SecureRng::from_seed([22u8; 32]);
+        let mut out = [0.0; 7];
+        batched.gen_normal(0.0, 1.0).unwrap();
+        batched.fill_normal(&mut out, 0.0, 1.0).unwrap();
+        single.gen_normal(0.0, 1.0).unwrap();
+        for &x in &out {
+            assert_eq!(x, single.gen_normal(0.0, 1.0).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_try_new_reports_allocation_failure() {
+        let mut rng = SecureRng::try_new().unwrap();
+        rng.next_u32().unwrap();
+
+        assert_eq!(try_alloc_buffer(BUFFER_SIZE).unwrap().capacity(), BUFFER_SIZE);
+        assert!(matches!(try_alloc_buffer(usize::MAX), Err(RngError::AllocationFailed)));
+    }
+
+    #[test]
+    fn test_sample_cdf() {
+        let mut rng = SecureRng::from_seed([24u8; 32]);
+        let cdf = [0.2, 0.5, 1.0];
+        let mut counts = [0u64; 3];
+        for _ in 0..100_000 {
+            counts[rng.sample_cdf(&cdf).unwrap()] += 1;
+        }
+        // Weights 0.2 / 0.3 / 0.5, each within ~5 standard errors
+        for (&count, expected) in counts.iter().zip([20_000.0, 30_000.0, 50_000.0]) {
+            assert!((count as f64 - expected).abs() < 800.0, "counts {:?}", counts);
+        }
+
+        // Zero-width entries are never chosen
+        for _ in 0..1000 {
+            assert_eq!(rng.sample_cdf(&[0.0, 0.0, 1.0, 1.0]).unwrap(), 2);
+        }
+
+        for bad in [&[][..], &[0.5, 0.2, 1.0], &[0.2, 0.5, 0.9], &[-0.1, 1.0], &[f64::NAN, 1.0]] {
+            assert!(matches!(rng.sample_cdf(bad), Err(RngError::InvalidRange)), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_gen_range_as_f64() {
+        let mut rng = SecureRng::from_seed([26u8; 32]);
+        let mut seen = [false; 5];
+        for _ in 0..1000 {
+            let x = rng.gen_range_as_f64(10, 15).unwrap();
+            assert_eq!(x.fract(), 0.0);
+            assert!((10.0..15.0).contains(&x));
+            seen[x as usize - 10] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert!(rng.gen_range_as_f64(3, 3).is_err());
+    }
+
+    #[test]
+    fn test_fork_discards_buffer() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static FAKE_GENERATION: Cell<u64> = const { Cell::new(100) };
+        }
+        fn fake_generation() -> u64 {
+            FAKE_GENERATION.with(Cell::get)
+        }
+
+        let mut rng = SecureRng::builder().fork_source(fake_generation).build();
+        rng.next_u32().unwrap();
+        rng.next_u32().unwrap();
+        assert_eq!(rng.os_reads(), 1);
+
+        // The "child" must not serve the 1016 bytes still buffered
+        FAKE_GENERATION.with(|generation| generation.set(101));
+        rng.next_u32().unwrap();
+        assert_eq!(rng.os_reads(), 2);
+        let mut buf = [0u8; 16];
+        rng.fill_bytes(&mut buf).unwrap();
+        assert_eq!(rng.os_reads(), 2);
+
+        FAKE_GENERATION.with(|generation| generation.set(102));
+        rng.fill_bytes(&mut buf).unwrap();
+        assert_eq!(rng.os_reads(), 3);
+
+        // ChaCha20Seeded rekeys from the OS rather than continuing its keystream
+        let mut chacha = SecureRng::builder()
+            .source(EntropySource::ChaCha20Seeded)
+            .fork_source(fake_generation)
+            .build();
+        chacha.next_u32().unwrap();
+        FAKE_GENERATION.with(|generation| generation.set(103));
+        chacha.next_u32().unwrap();
+        assert_eq!(chacha.os_reads(), 2);
+
+        // Seeded streams are unaffected
+        let mut seeded = SecureRng::builder().seed([27u8; 32]).fork_source(fake_generation).build();
+        let mut reference = SecureRng::from_seed([27u8; 32]);
+        seeded.next_u32().unwrap();
+        reference.next_u32().unwrap();
+        FAKE_GENERATION.with(|generation| generation.set(104));
+        assert_eq!(seeded.next_u32().unwrap(), reference.next_u32().unwrap());
+    }
+
+    #[test]
+    fn test_gen_simplex_point() {
+        let mut rng = SecureRng::from_seed([28u8; 32]);
+        let mut totals = [0.0; 4];
+        for _ in 0..20_000 {
+            let point = rng.gen_simplex_point(4).unwrap();
+            assert_eq!(point.len(), 4);
+            assert!(point.iter().all(|&x| x >= 0.0));
+            assert!((point.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            for (total, x) in totals.iter_mut().zip(&point) {
+                *total += x;
+            }
+        }
+        // Symmetric: every coordinate has mean 1/4 (standard error ~0.001)
+        for total in totals {
+            assert!((total / 20_000.0 - 0.25).abs() < 0.006, "totals {:?}", totals);
+        }
+
+        assert_eq!(rng.gen_simplex_point(1).unwrap(), vec![1.0]);
+        assert!(matches!(rng.gen_simplex_point(0), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_bytes_consumed() {
+        let mut rng = SecureRng::from_seed([29u8; 32]);
+        rng.next_u32().unwrap();
+        let mut buf = [0u8; 2000];
+        rng.fill_bytes(&mut buf).unwrap();
+        rng.next_u64().unwrap();
+        rng.next_u128().unwrap();
+        rng.gen_rgb().unwrap();
+        assert_eq!(rng.bytes_consumed(), 4 + 2000 + 8 + 16 + 3);
+
+        // Independent of how many refills it took
+        let mut os = SecureRng::new();
+        os.fill_bytes(&mut buf).unwrap();
+        os.next_u32().unwrap();
+        assert_eq!(os.bytes_consumed(), 2004);
+        assert_eq!(os.os_reads(), 2);
+    }
+
+    #[test]
+    fn test_non_finite_results_are_errors() {
+        let mut rng = SecureRng::from_seed([30u8; 32]);
+
+        // |z| > 1 overflows about a third of the time
+        let mut overflows = 0;
+        for _ in 0..100 {
+            match rng.gen_normal(0.0, f64::MAX) {
+                Ok(x) => assert!(x.is_finite()),
+                Err(RngError::NonFinite) => overflows += 1,
+                Err(e) => panic!("unexpected error {:?}", e),
+            }
+        }
+        assert!(overflows > 10, "only {} overflows", overflows);
+
+        let mut out = [0.0; 64];
+        assert!(matches!(
+            rng.fill_normal(&mut out, f64::MAX, f64::MAX),
+            Err(RngError::NonFinite)
+        ));
+        assert!(matches!(rng.gen_exponential(1e-320), Err(RngError::NonFinite)));
+        assert!(matches!(rng.gen_pareto(f64::MAX, 1e-300), Err(RngError::NonFinite)));
+        assert!(matches!(rng.gen_gamma(1e6, f64::MAX), Err(RngError::NonFinite)));
+    }
+
+    #[test]
+    fn test_large_fill_reads_directly() {
+        let mut rng = SecureRng::new();
+        rng.next_u32().unwrap();
+        let mut buf = vec![0u8; 1024 * 1024];
+        rng.fill_bytes(&mut buf).unwrap();
+
+        // Leftover buffered bytes first, then a single read for the rest
+        assert_eq!(rng.os_reads(), 2);
+        assert!(buf.chunks(64).all(|chunk| chunk.iter().any(|&b| b != 0)));
+        assert_eq!(rng.bytes_consumed(), 4 + 1024 * 1024);
+
+        // Small draws afterwards go back through the buffer
+        rng.next_u32().unwrap();
+        rng.next_u32().unwrap();
+        assert_eq!(rng.os_reads(), 3);
+    }
+
+    #[test]
+    fn test_empty_ranges_are_invalid_range() {
+        let mut rng = SecureRng::from_seed([32u8; 32]);
+        let invalid = |result: Result<u32, RngError>| matches!(result, Err(RngError::InvalidRange));
+
+        assert!(invalid(rng.gen_below(0)));
+        for (min, max) in [(0, 0), (5, 5), (u32::MAX, u32::MAX), (7, 3), (u32::MAX, 0)] {
+            assert!(invalid(rng.gen_range(min, max)), "gen_range({}, {})", min, max);
+            assert!(invalid(rng.gen_in(Range { start: min, end: max })));
+            assert!(matches!(rng.range_sampler(min, max), Err(RngError::InvalidRange)));
+            assert!(matches!(rng.gen_range_as_f64(min, max), Err(RngError::InvalidRange)));
+        }
+        for (min, max) in [(1, 0), (u32::MAX, 0), (5, 4)] {
+            assert!(invalid(rng.gen_range_inclusive(min, max)));
+            assert!(invalid(rng.gen_in(RangeInclusive::new(min, max))));
+        }
+        assert!(matches!(rng.gen_range_u128(0, 0), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_range_u128(9, 1), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_subset_mask(3, 4), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.sample_cdf(&[]), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_range_sampler_fastmod_matches_modulo() {
+        let divisors = [
+            1, 2, 3, 6, 7, 10, 52, 1000, 65_521, 1 << 31, (1 << 31) + 1,
+            u32::MAX - 1, u32::MAX,
+        ];
+        let values = (0..=u32::MAX).step_by(65_537).chain([1, u32::MAX - 1, u32::MAX]);
+        for value in values {
+            for &range in &divisors {
+                let sampler = RangeSampler::with_span(0, range);
+                assert_eq!(sampler.reduce(value), value % range, "{} % {}", value, range);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gen_sign_and_next_bits() {
+        let mut rng = SecureRng::from_seed([33u8; 32]);
+        let mut sum = 0i64;
+        for _ in 0..100_000 {
+            let sign = rng.gen_sign().unwrap();
+            assert!(sign == 1 || sign == -1);
+            sum += sign as i64;
+        }
+        // Standard deviation of the sum is ~316
+        assert!(sum.abs() < 1500, "sum of signs {}", sum);
+        // 32 signs per word drawn
+        assert_eq!(rng.bytes_consumed(), 100_000 / 32 * 4);
+
+        // Bits are the drawn word, lowest first
+        let mut reference = SecureRng::from_seed([34u8; 32]);
+        let word = reference.next_u32().unwrap();
+        let mut bits = SecureRng::from_seed([34u8; 32]);
+        assert_eq!(bits.next_bits(4).unwrap(), word & 0xf);
+        assert_eq!(bits.next_bits(28).unwrap(), word >> 4);
+        assert_eq!(bits.next_bits(32).unwrap(), reference.next_u32().unwrap());
+
+        assert!(matches!(bits.next_bits(0), Err(RngError::InvalidRange)));
+        assert!(matches!(bits.next_bits(33), Err(RngError::InvalidRange)));
+    }
+
+    // Each bit is set with probability 0.7 (xorshift-driven, so deterministic per thread)
+    fn biased_source(buf: &mut [u8]) -> io::Result<()> {
+        use std::cell::Cell;
+
+        thread_local! {
+            static STATE: Cell<u64> = const { Cell::new(0x9e37_79b9_7f4a_7c15) };
+        }
+        STATE.with(|state| {
+            let mut x = state.get();
+            for byte in buf.iter_mut() {
+                *byte = 0;
+                for bit in 0..8 {
+                    x ^= x << 13;
+                    x ^= x >> 7;
+                    x ^= x << 17;
+                    if x % 10 < 7 {
+                        *byte |= 1 << bit;
+                    }
+                }
+            }
+            state.set(x);
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_whitening_biased_source() {
+        let sample = |rng: &mut SecureRng| -> Vec<u32> {
+            (0..20_000).map(|_| rng.next_u32().unwrap()).collect()
+        };
+
+        let mut raw = SecureRng::builder()
+            .source(EntropySource::Callback(biased_source))
+            .build();
+        assert!(crate::stats::monobit_test(&sample(&mut raw)) < 1e-10);
+
+        let mut whitened = SecureRng::builder()
+            .source(EntropySource::Callback(biased_source))
+            .whiten(true)
+            .build();
+        let p = crate::stats::monobit_test(&sample(&mut whitened));
+        assert!(p > 1e-4, "whitened monobit p-value {}", p);
+
+        // Large fills are whitened too rather than read straight from the source
+        let mut large = vec![0u8; 4096];
+        whitened.fill_bytes(&mut large).unwrap();
+        assert!(large.chunks(32).all(|chunk| chunk.iter().any(|&b| b != 0)));
+    }
+
+    #[test]
+    fn test_whitening_keeps_partial_block() {
+        // 100 raw bytes: one full block and a 36-byte tail, served as 32 + 18
+        let raw: Vec<u8> = (0..100).collect();
+        let mut rng = SecureRng::from_reader(io::Cursor::new(raw.clone()));
+        rng.whiten_counter = Some(0);
+        let mut out = vec![0u8; 50];
+        rng.fill_bytes(&mut out).unwrap();
+        assert!(matches!(rng.next_u32(), Err(RngError::IoError { .. })));
+
+        let mut hasher = Sha256::new();
+        hasher.update(&1u64.to_le_bytes());
+        hasher.update(&raw[64..]);
+        assert_eq!(out[32..], hasher.finalize()[..18]);
+
+        // An odd tail rounds up rather than dropping its last byte
+        let mut buffer = vec![7u8; 65];
+        let mut counter = 0;
+        whiten_in_place(&mut buffer, &mut counter);
+        assert_eq!((buffer.len(), counter), (33, 2));
+    }
+
+    #[test]
+    fn test_min_entropy_estimate() {
+        let mut uniform = SecureRng::from_seed([46u8; 32]);
+        let h = uniform.min_entropy_estimate(1_000_000).unwrap();
+        assert!(h > 7.8 && h <= 8.0, "uniform min-entropy {}", h);
+
+        // 70% ones per bit: 0xff has probability 0.7^8, about 4.2 bits
+        let mut biased = SecureRng::builder()
+            .source(EntropySource::Callback(biased_source))
+            .build();
+        let h = biased.min_entropy_estimate(100_000).unwrap();
+        assert!(h < 4.5, "biased min-entropy {}", h);
+
+        for sample in [0, 1, 2, MIN_ENTROPY_SAMPLE - 1] {
+            assert!(matches!(uniform.min_entropy_estimate(sample), Err(RngError::InvalidRange)));
+        }
+        let h = uniform.min_entropy_estimate(MIN_ENTROPY_SAMPLE).unwrap();
+        assert!(h > 0.0 && h.is_sign_positive(), "min-entropy {}", h);
+    }
+
+    #[test]
+    fn test_from_reader() {
+        use std::io::Cursor;
+
+        let data: Vec<u8> = (0..1500u32).map(|i| (i * 7 + 3) as u8).collect();
+        let mut rng = SecureRng::from_reader(Cursor::new(data.clone()));
+        let mut other = SecureRng::from_reader(Cursor::new(data.clone()));
+
+        let first = rng.next_u32().unwrap();
+        assert_eq!(first, u32::from_ne_bytes(data[..4].try_into().unwrap()));
+        assert_eq!(first, other.next_u32().unwrap());
+
+        // The partial second refill is served before the reader reports exhaustion
+        let mut rest = vec![0u8; data.len() - 4];
+        rng.fill_bytes(&mut rest).unwrap();
+        assert_eq!(rest, data[4..]);
+        assert_eq!(rng.os_reads(), 2);
+
+        let err = rng.next_u32().unwrap_err();
+        assert!(matches!(err, RngError::IoError { context: "reading entropy reader", .. }));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut empty = SecureRng::from_reader(io::empty());
+        assert!(empty.next_u32().is_err());
+    }
+
+    #[test]
+    fn test_io_errors_carry_context() {
+        fn failing_source(_: &mut [u8]) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::TimedOut, "device busy"))
+        }
+
+        let mut rng = SecureRng::builder()
+            .source(EntropySource::Callback(failing_source))
+            .build();
+        let err = rng.next_u32().unwrap_err();
+        assert!(matches!(err, RngError::IoError { context: "entropy callback", .. }));
+
+        let converted = io::Error::from(err);
+        assert_eq!(converted.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(converted.to_string(), "entropy callback: device busy");
+
+        // A directory opens fine on Unix but cannot be read
+        #[cfg(unix)]
+        {
+            let mut dir = SecureRng::builder()
+                .source(EntropySource::Device(std::env::temp_dir()))
+                .build();
+            assert!(matches!(
+                dir.next_u32(),
+                Err(RngError::IoError { context: "reading entropy device", .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_gen_range_auto() {
+        let mut rng = SecureRng::from_seed([35u8; 32]);
+        let ranges = [
+            (0, 1), (0, 2), (10, 17), (0, 1000), (5, 1 << 20), (0, (1 << 31) - 1),
+            (0, 1 << 31), (1, u32::MAX), (0, u32::MAX),
+        ];
+        for (min, max) in ranges {
+            for _ in 0..1000 {
+                let value = rng.gen_range_auto(min, max).unwrap();
+                assert!((min..max).contains(&value), "{} not in {}..{}", value, min, max);
+            }
+        }
+
+        // Lemire path: the high half of word * 6, redrawing when the low half
+        // is below 2^32 mod 6 = 4 (word 0 gives low half 0)
+        assert!(uses_lemire(6));
+        let mut words = SecureRng::from_u32_stream(vec![0x8000_0001, u32::MAX, 0, 0x2aaa_aaac]);
+        assert_eq!(words.gen_range_auto(10, 16).unwrap(), 13);
+        assert_eq!(words.gen_range_auto(10, 16).unwrap(), 15);
+        assert_eq!(words.gen_range_auto(10, 16).unwrap(), 11);
+        assert!(words.next_u32().is_err());
+
+        // Classic path: word % span, redrawing above rejection_threshold
+        let span = 3 << 30;
+        assert!(!uses_lemire(span));
+        assert_eq!(rejection_threshold(span), span - 1);
+        let mut words = SecureRng::from_u32_stream(vec![span + 1, 5, span - 1]);
+        assert_eq!(words.gen_range_auto(7, span + 7).unwrap(), 12);
+        assert_eq!(words.gen_range_auto(7, span + 7).unwrap(), span + 6);
+        assert!(words.next_u32().is_err());
+
+        // Each path is uniform over its own span
+        crate::stats::assert_uniform_with(&mut rng, 0, 6, 60_000, 1e-4, |rng| rng.gen_range_auto(0, 6));
+        crate::stats::assert_uniform_with(&mut rng, 0, span, 100_000, 1e-4, |rng| {
+            rng.gen_range_auto(0, span)
+        });
+
+        assert!(matches!(rng.gen_range_auto(4, 4), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_unit_disk_and_square() {
+        let mut rng = SecureRng::from_seed([36u8; 32]);
+        let n = 100_000;
+        let mut inside = 0;
+        let mut quadrants = [0u32; 4];
+        for _ in 0..n {
+            let (x, y) = rng.gen_unit_disk().unwrap();
+            assert!(x * x + y * y <= 1.0);
+            quadrants[(x >= 0.0) as usize * 2 + (y >= 0.0) as usize] += 1;
+
+            let (u, v) = rng.gen_unit_square().unwrap();
+            assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+            if u * u + v * v <= 1.0 {
+                inside += 1;
+            }
+        }
+
+        // Quarter-disk area over the unit square: pi / 4 (standard error ~0.0013)
+        let ratio = inside as f64 / n as f64;
+        assert!((ratio - std::f64::consts::FRAC_PI_4).abs() < 0.007, "ratio {}", ratio);
+        // Disk points split evenly by quadrant (25_000 +- ~137 each)
+        assert!(quadrants.iter().all(|&q| (q as i64 - 25_000).abs() < 700), "{:?}", quadrants);
+
+        // Radius of a uniform disk point: P(r <= 1/2) = 1/4, not 1/2 as for polar draws
+        let near_centre = (0..n)
+            .filter(|_| {
+                let (x, y) = rng.gen_unit_disk().unwrap();
+                x * x + y * y <= 0.25
+            })
+            .count();
+        assert!((near_centre as f64 / n as f64 - 0.25).abs() < 0.007);
+    }
+
+    #[test]
+    fn test_unit_vector_3d() {
+        let mut rng = SecureRng::from_seed([37u8; 32]);
+        let n = 50_000;
+        let mut sums = [0.0; 3];
+        let mut positive = [0u32; 3];
+        let mut upper_cap = 0;
+        for _ in 0..n {
+            let v = rng.gen_unit_vector_3d().unwrap();
+            let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+            assert!((length - 1.0).abs() < 1e-12);
+            for axis in 0..3 {
+                sums[axis] += v[axis];
+                positive[axis] += (v[axis] > 0.0) as u32;
+            }
+            // Archimedes: a uniform point has z uniform on [-1, 1]
+            upper_cap += (v[2] > 0.5) as u32;
+        }
+
+        for axis in 0..3 {
+            assert!((sums[axis] / n as f64).abs() < 0.015, "mean of axis {}", axis);
+            assert!((positive[axis] as i64 - 25_000).abs() < 600, "{:?}", positive);
+        }
+        assert!((upper_cap as f64 / n as f64 - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_quality_report() {
+        let mut rng = SecureRng::builder().seed([36u8; 32]).build();
+        let report = rng.quality_report(20_000).unwrap();
+        assert_eq!(report.sample_size, 20_000);
+        assert!(report.passed, "{:?}", report);
+        assert_eq!(rng.bytes_consumed(), 80_000);
+
+        assert!(matches!(rng.quality_report(1), Err(RngError::InvalidRange)));
+
+        // Live entropy can legitimately fail at 1e-4, so only the shape is checked
+        let mut live = SecureRng::new();
+        let report = live.quality_report(20_000).unwrap();
+        assert_eq!(report.sample_size, 20_000);
+        let p_values = [report.monobit_p_value, report.runs_p_value, report.autocorrelation_p_value];
+        assert!(p_values.iter().all(|p| (0.0..=1.0).contains(p)), "{:?}", report);
+        assert_eq!(report.passed, p_values.iter().all(|&p| p >= 1e-4));
+    }
+
+    #[test]
+    fn test_from_u32_stream() {
+        // Draws for [0, 10) are accepted up to rejection_threshold(10) = u32::MAX - 6
+        // (2^32 mod 10 = 6), so u32::MAX is rejected and redrawn
+        assert_eq!(rejection_threshold(10), u32::MAX - 6);
+        let values = vec![3, 17, u32::MAX, 42, 0xdead_beef];
+        let mut rng = SecureRng::from_u32_stream(values.clone());
+        let mut copy = rng.clone_stream();
+        assert_eq!(rng.gen_range(0, 10).unwrap(), 3);
+        assert_eq!(rng.gen_range(0, 10).unwrap(), 7);
+        assert_eq!(rng.gen_range(0, 10).unwrap(), 2);
+        assert_eq!(rng.next_u32().unwrap(), 0xdead_beef);
+        assert!(matches!(rng.next_u32(), Err(RngError::EntropyError)));
+        assert!(matches!(rng.next_u32(), Err(RngError::EntropyError)));
+
+        for value in values {
+            assert_eq!(copy.next_u32().unwrap(), value);
+        }
+        assert!(SecureRng::from_u32_stream(Vec::new()).next_u32().is_err());
+    }
+
+    #[test]
+    fn test_drain_into() {
+        let mut rng = SecureRng::from_seed([38u8; 32]);
+        let mut reference = SecureRng::from_seed([38u8; 32]);
+        let mut key = [0u8; 32];
+        let mut expected = [0u8; 32];
+        rng.next_u64().unwrap();
+        reference.next_u64().unwrap();
+        rng.drain_into(&mut key).unwrap();
+        reference.fill_bytes(&mut expected).unwrap();
+        assert_eq!(key, expected);
+
+        // Best effort: inspect the buffer the key was copied out of
+        let served = &rng.buffer[8..40];
+        if cfg!(feature = "zeroize") {
+            assert!(served.iter().all(|&b| b == 0));
+        } else {
+            assert_eq!(served, key);
+        }
+        // Bytes not yet handed out are untouched either way
+        assert_eq!(rng.next_u64().unwrap(), reference.next_u64().unwrap());
+    }
+
+    #[test]
+    fn test_gen_sorted_uniform() {
+        let mut rng = SecureRng::from_seed([39u8; 32]);
+        let n = 10_000;
+        let sorted = rng.gen_sorted_uniform(n).unwrap();
+        assert_eq!(sorted.len(), n);
+        assert!(sorted.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(sorted.iter().all(|x| (0.0..1.0).contains(x)));
+
+        // Scaled by n + 1 the spacings are ~Exponential(1): mean 1, P(> 1) = 1/e
+        let spacings: Vec<f64> = std::iter::once(0.0)
+            .chain(sorted.iter().copied())
+            .collect::<Vec<f64>>()
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]) * (n + 1) as f64)
+            .collect();
+        crate::stats::assert_distribution_moments(&spacings, 1.0, 1.0, 0.1).unwrap();
+        let above_mean = spacings.iter().filter(|&&x| x > 1.0).count() as f64 / n as f64;
+        assert!((above_mean - (-1f64).exp()).abs() < 0.015, "P(spacing > 1) = {}", above_mean);
+
+        // Order statistic k has mean k / (n + 1)
+        assert!((sorted[n / 2 - 1] - 0.5).abs() < 0.02);
+        assert!(rng.gen_sorted_uniform(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_next_u32x4() {
+        let mut rng = SecureRng::from_seed([40u8; 32]);
+        let mut reference = SecureRng::from_seed([40u8; 32]);
+
+        let mut lane_totals = [0u64; 4];
+        for _ in 0..1000 {
+            let lanes = rng.next_u32x4().unwrap();
+            let mut bytes = [0u8; 16];
+            reference.fill_bytes(&mut bytes).unwrap();
+            for (lane, chunk) in lanes.iter().zip(bytes.chunks_exact(4)) {
+                assert_eq!(*lane, u32::from_le_bytes(chunk.try_into().unwrap()));
+            }
+            assert!(lanes[0] != lanes[1] || lanes[1] != lanes[2] || lanes[2] != lanes[3]);
+            for (total, &lane) in lane_totals.iter_mut().zip(&lanes) {
+                *total += lane as u64;
+            }
+        }
+        // One 16-byte read per call
+        assert_eq!(rng.bytes_consumed(), 16_000);
+        // Every lane averages near 2^31
+        for total in lane_totals {
+            assert!((total as f64 / 1000.0 / 2f64.powi(31) - 1.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_gen_range_debug_consumption() {
+        let words = [u32::MAX, 123, 5, 9];
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_ne_bytes()).collect();
+        let mut rng = SecureRng::from_fixed_bytes(bytes);
+        assert_eq!(rng.buffered_remaining(), 16);
+
+        // u32::MAX is past the rejection threshold for [0, 10): two draws, 8 bytes
+        assert_eq!(rng.gen_range_debug(0, 10).unwrap(), (3, 8));
+        assert_eq!(rng.gen_range_debug(0, 10).unwrap(), (5, 4));
+        assert_eq!(rng.gen_range_debug(0, 10).unwrap(), (9, 0));
+        assert!(matches!(rng.gen_range_debug(0, 10), Err(RngError::EntropyError)));
+    }
+
+    #[test]
+    fn test_with_fixed_bytes() {
+        let mut rng = SecureRng::new();
+        rng.next_u32().unwrap();
+        let remaining = rng.buffered_remaining();
+
+        let injected: Vec<u8> = [7u32, 12].iter().flat_map(|v| v.to_ne_bytes()).collect();
+        let (die, exhausted) = rng.with_fixed_bytes(&injected, |rng| {
+            let die = rng.gen_in(1..=6).unwrap();
+            assert_eq!(rng.next_u32().unwrap(), 12);
+            (die, rng.next_u32().is_err())
+        });
+        assert_eq!(die, 2);
+        assert!(exhausted);
+
+        // The OS source resumes from where it left off
+        assert_eq!(rng.buffered_remaining(), remaining);
+        rng.next_u32().unwrap();
+        assert_eq!(rng.os_reads(), 1);
+        assert_eq!(rng.source(), EntropySource::Os);
+
+        // ...even when `f` panics and the panic is caught
+        let remaining = rng.buffered_remaining();
+        let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            rng.with_fixed_bytes(&injected, |rng| {
+                rng.next_u32().unwrap();
+                panic!("sampler under test failed");
+            })
+        }));
+        assert!(caught.is_err());
+        assert_eq!(rng.buffered_remaining(), remaining);
+        assert_eq!(rng.source(), EntropySource::Os);
+        for _ in 0..10 {
+            rng.next_u32().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_gen_range_i64_extremes() {
+        let mut rng = SecureRng::from_seed([41u8; 32]);
+
+        let mut seen = [false; 10];
+        for _ in 0..1000 {
+            let value = rng.gen_range_i64(i64::MIN, i64::MIN + 10).unwrap();
+            assert!((i64::MIN..i64::MIN + 10).contains(&value));
+            seen[(value - i64::MIN) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+
+        let (mut negative, mut positive) = (0, 0);
+        for _ in 0..1000 {
+            let value = rng.gen_range_i64(i64::MIN, i64::MAX).unwrap();
+            assert!(value < i64::MAX);
+            if value < 0 {
+                negative += 1;
+            } else {
+                positive += 1;
+            }
+        }
+        assert!(negative > 400 && positive > 400);
+
+        for _ in 0..1000 {
+            let value = rng.gen_range_i64(i64::MAX - 3, i64::MAX).unwrap();
+            assert!((i64::MAX - 3..i64::MAX).contains(&value));
+            assert!((-5..5).contains(&rng.gen_range_i64(-5, 5).unwrap()));
+        }
+        assert!(matches!(rng.gen_range_i64(3, 3), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_range_i64(i64::MAX, i64::MIN), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_permute_in_place() {
+        let mut rng = SecureRng::from_seed([42u8; 32]);
+        let original: Vec<String> = (0..20).map(|i| format!("item{}", i)).collect();
+        let labels: Vec<u32> = (100..120).collect();
+
+        let mut shuffled = original.clone();
+        let perm = rng.permute_in_place(&mut shuffled).unwrap();
+        assert_ne!(shuffled, original);
+
+        let mut sorted = perm.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..20).collect::<Vec<usize>>());
+
+        // Applying perm to the original order reproduces the shuffle, and
+        // keeps a paired array aligned with it
+        let reproduced: Vec<String> = perm.iter().map(|&i| original[i].clone()).collect();
+        assert_eq!(reproduced, shuffled);
+        let paired: Vec<u32> = perm.iter().map(|&i| labels[i]).collect();
+        for (item, label) in shuffled.iter().zip(&paired) {
+            assert_eq!(item[4..].parse::<u32>().unwrap() + 100, *label);
+        }
+
+        assert!(rng.permute_in_place::<u8>(&mut []).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejection_threshold() {
+        assert_eq!(rejection_threshold(1), u32::MAX);
+        // 2^32 = 3 * 1431655765 + 1: one value left over
+        assert_eq!(rejection_threshold(3), u32::MAX - 1);
+        assert_eq!(rejection_threshold(1 << 31), u32::MAX);
+        // 2^32 = 1 * (2^32 - 1) + 1
+        assert_eq!(rejection_threshold(u32::MAX), u32::MAX - 1);
+        assert_eq!(rejection_threshold(10), u32::MAX - 6);
+
+        // Accepted count is the largest multiple of range not above 2^32
+        for range in [1, 2, 3, 7, 10, 52, 1000, 1 << 31, (1 << 31) + 1, u32::MAX - 1, u32::MAX] {
+            let accepted = rejection_threshold(range) as u64 + 1;
+            assert_eq!(accepted % range as u64, 0, "range {}", range);
+            assert!(accepted + range as u64 > 1 << 32, "range {}", range);
+        }
+
+        // Power-of-two spans never reject
+        let words = [u32::MAX, 5];
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_ne_bytes()).collect();
+        let mut rng = SecureRng::from_fixed_bytes(bytes);
+        assert_eq!(rng.gen_range(0, 1 << 31).unwrap(), (1 << 31) - 1);
+        assert_eq!(rng.gen_range(0, 3).unwrap(), 2);
+    }
+
+    // Chi-square p-value of how often each of the 24 orderings of [0, 1, 2, 3]
+    // comes out of `shuffle`
+    fn permutation_uniformity(mut shuffle: impl FnMut(&mut [u8; 4])) -> f64 {
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..240_000 {
+            let mut items = [0, 1, 2, 3];
+            shuffle(&mut items);
+            *counts.entry(items).or_insert(0u64) += 1;
+        }
+        let mut counts: Vec<u64> = counts.into_values().collect();
+        counts.resize(24, 0);
+        let chi_square = crate::stats::chi_square_statistic(&counts);
+        crate::stats::chi_square_p_value(chi_square, 23.0)
+    }
+
+    #[test]
+    fn test_shuffle_permutations_equally_likely() {
+        let mut rng = SecureRng::from_seed([43u8; 32]);
+        let p = permutation_uniformity(|items| rng.shuffle(items).unwrap());
+        assert!(p > 1e-4, "shuffle p-value {}", p);
+
+        // The classic bug, swapping with any index instead of 0..=i, spreads
+        // 4^3 = 64 equally likely paths over 24 orderings: caught at once
+        let mut rng = SecureRng::from_seed([43u8; 32]);
+        let p = permutation_uniformity(|items| {
+            for i in (1..items.len()).rev() {
+                let j = rng.gen_index(items.len()).unwrap();
+                items.swap(i, j);
+            }
+        });
+        assert!(p < 1e-10, "naive shuffle p-value {}", p);
+    }
+
+    #[test]
+    fn test_gen_range_n() {
+        let mut rng = SecureRng::from_seed([44u8; 32]);
+        let mut reference = SecureRng::from_seed([44u8; 32]);
+        let values: [u32; 64] = rng.gen_range_n(100, 110).unwrap();
+        assert!(values.iter().all(|v| (100..110).contains(v)));
+        // Same draws as separate gen_range calls
+        for &value in &values {
+            assert_eq!(value, reference.gen_range(100, 110).unwrap());
+        }
+
+        assert_eq!(rng.gen_range_n::<0>(0, 10).unwrap(), []);
+        assert!(matches!(rng.gen_range_n::<4>(10, 10), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_fold_entropy_diverges_stream() {
+        // A constant source makes the unseeded path deterministic enough to compare
+        fn zero_source(buf: &mut [u8]) -> io::Result<()> {
+            buf.fill(0);
+            Ok(())
+        }
+        let stream = |extra: Option<&[u8]>| -> Vec<u32> {
+            let mut rng = SecureRng::builder()
+                .source(EntropySource::Callback(zero_source))
+                .build();
+            if let Some(extra) = extra {
+                rng.fold_entropy(extra).unwrap();
+            }
+            (0..64).map(|_| rng.next_u32().unwrap()).collect()
+        };
+
+        // Folding before anything is buffered still reaches every later refill
+        let mut rng = SecureRng::builder()
+            .source(EntropySource::Callback(zero_source))
+            .build();
+        rng.fold_entropy(b"mouse:12,40").unwrap();
+        // BUFFER_SIZE words span four refills
+        let values: Vec<u32> = (0..BUFFER_SIZE).map(|_| rng.next_u32().unwrap()).collect();
+        assert!(crate::stats::monobit_test(&values) > 1e-6);
+        let refills = values.chunks(BUFFER_SIZE / 4).collect::<Vec<_>>();
+        assert!(refills.iter().all(|refill| refill.iter().any(|&x| x != 0)));
+        assert_ne!(refills[0], refills[1]);
+
+        let a = stream(Some(b"mouse:12,40"));
+        assert_eq!(a, stream(Some(b"mouse:12,40")));
+        assert_ne!(a, stream(Some(b"mouse:12,41")));
+        assert!(stream(None).iter().all(|&x| x == 0));
+        assert!(a.iter().any(|&x| x != 0));
+
+        let mut seeded = SecureRng::from_seed([9u8; 32]);
+        let mut other = SecureRng::from_seed([9u8; 32]);
+        seeded.fold_entropy(b"tick:1").unwrap();
+        other.fold_entropy(b"tick:2").unwrap();
+        assert_ne!(seeded.next_u64().unwrap(), other.next_u64().unwrap());
+
+        // A second fold changes the keystream for the rest of the stream too
+        let mut once = SecureRng::builder()
+            .source(EntropySource::Callback(zero_source))
+            .build();
+        let mut twice = SecureRng::builder()
+            .source(EntropySource::Callback(zero_source))
+            .build();
+        once.fold_entropy(b"key:a").unwrap();
+        twice.fold_entropy(b"key:a").unwrap();
+        twice.fold_entropy(b"key:b").unwrap();
+        let mut first = vec![0u8; 3 * BUFFER_SIZE];
+        let mut second = vec![0u8; 3 * BUFFER_SIZE];
+        once.fill_bytes(&mut first).unwrap();
+        twice.fill_bytes(&mut second).unwrap();
+        assert_ne!(first[2 * BUFFER_SIZE..], second[2 * BUFFER_SIZE..]);
+    }
+
+    #[test]
+    fn test_gen_correlated_normals() {
+        let mut rng = SecureRng::from_seed([45u8; 32]);
+        let n = 50_000;
+        for rho in [-0.9, 0.0, 0.5, 1.0] {
+            let pairs: Vec<(f64, f64)> = (0..n)
+                .map(|_| rng.gen_correlated_normals(rho).unwrap())
+                .collect();
+            let mean = |f: fn(&(f64, f64)) -> f64| pairs.iter().map(f).sum::<f64>() / n as f64;
+            let (mx, my) = (mean(|p| p.0), mean(|p| p.1));
+            let cov = pairs.iter().map(|&(x, y)| (x - mx) * (y - my)).sum::<f64>();
+            let var_x = pairs.iter().map(|&(x, _)| (x - mx).powi(2)).sum::<f64>();
+            let var_y = pairs.iter().map(|&(_, y)| (y - my).powi(2)).sum::<f64>();
+            let r = cov / (var_x * var_y).sqrt();
+            assert!((r - rho).abs() < 0.02, "rho {}: empirical correlation {}", rho, r);
+            // The second value stays a standard normal
+            assert!((var_y / n as f64 - 1.0).abs() < 0.05);
+        }
+
+        for rho in [1.5, -1.01, f64::NAN] {
+            assert!(matches!(rng.gen_correlated_normals(rho), Err(RngError::InvalidRange)));
+        }
+    }
+
+    #[test]
+    fn test_gen_die() {
+        let mut rng = SecureRng::from_seed([47u8; 32]);
+        let rolls = 60_000;
+        let mut counts = [0u64; 6];
+        for _ in 0..rolls {
+            counts[rng.gen_die(6).unwrap() as usize - 1] += 1;
+        }
+        let chi_square = crate::stats::chi_square_statistic(&counts);
+        assert!(crate::stats::chi_square_p_value(chi_square, 5.0) > 1e-4, "{:?}", counts);
+        // 24 rolls per accepted word, with about 23% of words rejected
+        assert!(rng.bytes_consumed() < rolls / 24 * 8 * 2);
+
+        let mut os = SecureRng::new();
+        for _ in 0..rolls {
+            assert!((1..=6).contains(&os.gen_die(6).unwrap()));
+        }
+        assert!(os.os_reads() < rolls / 500, "{} reads", os.os_reads());
+
+        // Switching dice keeps each face range intact
+        for _ in 0..1000 {
+            assert!((1..=2).contains(&rng.gen_die(2).unwrap()));
+            assert!((1..=20).contains(&rng.gen_die(20).unwrap()));
+        }
+        assert_eq!(die_packing(2), (1 << 63, 63));
+        assert_eq!(die_packing(u32::MAX).1, 2);
+        assert_eq!(rng.gen_die(1).unwrap(), 1);
+        assert!(matches!(rng.gen_die(0), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_estimated_draws_remaining() {
+        let mut rng = SecureRng::new();
+        assert_eq!(rng.estimated_draws_remaining(8), 0);
+        rng.next_u32().unwrap();
+        assert_eq!(rng.os_reads(), 1);
+
+        let remaining = rng.estimated_draws_remaining(8);
+        assert_eq!(remaining, (BUFFER_SIZE - 4) / 8);
+        for _ in 0..remaining {
+            rng.next_u64().unwrap();
+        }
+        assert_eq!(rng.os_reads(), 1);
+        assert_eq!(rng.estimated_draws_remaining(8), 0);
+        rng.next_u64().unwrap();
+        assert_eq!(rng.os_reads(), 2);
+
+        // Pre-warming refills the buffer ahead of the next draw
+        rng.reseed().unwrap();
+        assert_eq!(rng.estimated_draws_remaining(4), BUFFER_SIZE / 4);
+        assert_eq!(rng.estimated_draws_remaining(0), usize::MAX);
+    }
+
+    #[test]
+    fn test_gen_range_widening() {
+        let mut rng = SecureRng::from_seed([48u8; 32]);
+        let draws = 60_000;
+        let mut counts = [0u64; 6];
+        for _ in 0..draws {
+            let value = rng.gen_range_widening(10, 16).unwrap();
+            counts[value as usize - 10] += 1;
+        }
+        let chi_square = crate::stats::chi_square_statistic(&counts);
+        assert!(crate::stats::chi_square_p_value(chi_square, 5.0) > 1e-4, "{:?}", counts);
+        // One word per call: never loops
+        assert_eq!(rng.bytes_consumed(), draws * 8);
+
+        // Extreme words map to the ends of the range
+        let mut rng = SecureRng::from_u32_stream(vec![0, 0, u32::MAX, u32::MAX]);
+        assert_eq!(rng.gen_range_widening(5, u32::MAX).unwrap(), 5);
+        assert_eq!(rng.gen_range_widening(5, u32::MAX).unwrap(), u32::MAX - 1);
+        assert!(matches!(rng.gen_range_widening(3, 3), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_checkpoint_restore_replays() {
+        let mut rng = SecureRng::new();
+        rng.reseed().unwrap();
+        rng.gen_normal(0.0, 1.0).unwrap();
+        let checkpoint = rng.checkpoint();
+
+        let draw = |rng: &mut SecureRng| -> (Vec<u32>, f64, u32, u32) {
+            let values = (0..100).map(|_| rng.next_u32().unwrap()).collect();
+            let normal = rng.gen_normal(0.0, 1.0).unwrap();
+            (values, normal, rng.next_bits(3).unwrap(), rng.gen_die(6).unwrap())
+        };
+        let first = draw(&mut rng);
+        let consumed = rng.bytes_consumed();
+
+        rng.restore(checkpoint.clone());
+        assert_eq!(draw(&mut rng), first);
+        assert_eq!(rng.bytes_consumed(), consumed);
+        rng.restore(checkpoint);
+        assert_eq!(draw(&mut rng), first);
+
+        // Seeded replays run past the buffer they were taken with
+        let mut seeded = SecureRng::from_seed([49u8; 32]);
+        let checkpoint = seeded.checkpoint();
+        let long: Vec<u64> = (0..1000).map(|_| seeded.next_u64().unwrap()).collect();
+        seeded.restore(checkpoint);
+        let replay: Vec<u64> = (0..1000).map(|_| seeded.next_u64().unwrap()).collect();
+        assert_eq!(long, replay);
+
+        // A checkpoint from before a fork is not replayed in the child
+        use std::cell::Cell;
+        thread_local! {
+            static FAKE_GENERATION: Cell<u64> = const { Cell::new(200) };
+        }
+        fn fake_generation() -> u64 {
+            FAKE_GENERATION.with(Cell::get)
+        }
+        let mut rng = SecureRng::builder().fork_source(fake_generation).build();
+        rng.reseed().unwrap();
+        let checkpoint = rng.checkpoint();
+        let parent = rng.next_u64().unwrap();
+        FAKE_GENERATION.with(|generation| generation.set(201));
+        rng.next_u32().unwrap();
+        rng.restore(checkpoint);
+        assert_ne!(rng.next_u64().unwrap(), parent);
+    }
+
+    #[test]
+    fn test_pareto_rejects_invalid_parameters() {
+        let mut rng = SecureRng::new();
+        assert!(matches!(rng.gen_pareto(0.0, 1.0), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_pareto(1.0, 0.0), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_pareto(-1.0, 2.0), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_pareto(1.0, f64::NAN), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_pareto(1.0, f64::INFINITY), Err(RngError::InvalidRange)));
+        assert!(matches!(rng.gen_pareto(f64::INFINITY, 1.0), Err(RngError::InvalidRange)));
+    }
+
+    #[test]
+    fn test_pareto_is_bounded_below_and_heavy_tailed() {
+        let mut rng = SecureRng::new();
+        let scale = 2.0;
+        let shape = 1.5;
+        let samples = 100_000;
+
+        let mut beyond_tenfold = 0;
+        let mut largest = 0.0f64;
+        for _ in 0..samples {
+            let x = rng.gen_pareto(scale, shape).unwrap();
+            assert!(x >= scale, "sample {} below scale {}", x, scale);
+            if x > 10.0 * scale {
+                beyond_tenfold += 1;
+            }
+            largest = largest.max(x);
+        }
+
+        // P(X > 10 * scale) = 10^-1.5 ~ 3.16%, far above any light-tailed law
+        let fraction = beyond_tenfold as f64 / samples as f64;
+        assert!(fraction > 0.025 && fraction < 0.04, "tail fraction {}", fraction);
+        assert!(largest > 100.0 * scale, "largest sample {}", largest);
+    }
+}
@@ -0,0 +1,754 @@
+use crate::{RngError, SecureRng};
+
+// Histogram of set-bit counts: entry k is how many values have exactly k of
+// their 32 bits set. For uniform input this approximates binomial(32, 0.5).
+pub fn popcount_distribution(numbers: &[u32]) -> Vec<u64> {
+    let mut histogram = vec![0u64; 33];
+    for &num in numbers {
+        histogram[num.count_ones() as usize] += 1;
+    }
+    histogram
+}
+
+// Shannon entropy of the bytes making up the values, in bits per byte
+pub fn byte_entropy(numbers: &[u32]) -> f64 {
+    let mut byte_counts = [0u64; 256];
+    for &num in numbers {
+        for byte in num.to_ne_bytes() {
+            byte_counts[byte as usize] += 1;
+        }
+    }
+
+    let total = (numbers.len() * 4) as f64;
+    byte_counts.iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let probability = count as f64 / total;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+// NIST SP 800-90B most-common-value estimate of min-entropy, in bits per
+// byte: -log2 of the 99% upper confidence bound on the most common byte's
+// probability. Conservative by design, so a perfect source lands just under 8.
+pub fn min_entropy_mcv(bytes: &[u8]) -> f64 {
+    let mut byte_counts = [0u64; 256];
+    for &byte in bytes {
+        byte_counts[byte as usize] += 1;
+    }
+
+    let n = bytes.len() as f64;
+    let p_hat = *byte_counts.iter().max().unwrap() as f64 / n;
+    let p_upper = (p_hat + 2.576 * (p_hat * (1.0 - p_hat) / (n - 1.0)).sqrt()).min(1.0);
+    // Subtracted from +0.0 so a certain byte reports 0 bits, not -0
+    0.0 - p_upper.log2()
+}
+
+// NIST SP 800-22 frequency (monobit) test: p-value for the balance of ones
+// and zeros across the whole bitstream
+pub fn monobit_test(numbers: &[u32]) -> f64 {
+    let n = (numbers.len() * 32) as f64;
+    let ones: u64 = numbers.iter().map(|&num| num.count_ones() as u64).sum();
+    let sum = 2.0 * ones as f64 - n;
+    two_sided_p_value(sum / n.sqrt())
+}
+
+// Pearson chi-square statistic of the counts against an even split
+pub fn chi_square_statistic(counts: &[u64]) -> f64 {
+    let total: u64 = counts.iter().sum();
+    let expected = total as f64 / counts.len() as f64;
+    counts.iter()
+        .map(|&count| (count as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+// Wald-Wolfowitz runs test over the bitstream: z-score of the number of runs
+// of identical bits. Too few runs means clumping, too many means alternation.
+pub fn runs_z_score(numbers: &[u32]) -> f64 {
+    let n = (numbers.len() * 32) as f64;
+    let ones: u64 = numbers.iter().map(|&num| num.count_ones() as u64).sum();
+    let zeros = n - ones as f64;
+    let ones = ones as f64;
+
+    let mut runs = 0u64;
+    let mut previous = None;
+    for bit in bitstream(numbers) {
+        if previous != Some(bit) {
+            runs += 1;
+            previous = Some(bit);
+        }
+    }
+
+    let expected = 2.0 * ones * zeros / n + 1.0;
+    let variance = (expected - 1.0) * (expected - 2.0) / (n - 1.0);
+    (runs as f64 - expected) / variance.sqrt()
+}
+
+// Autocorrelation of the values at the given lag (NaN for a constant sample)
+pub fn autocorrelation(numbers: &[u32], lag: usize) -> f64 {
+    let total = numbers.len() as f64;
+    let mean = numbers.iter().map(|&x| x as f64).sum::<f64>() / total;
+
+    let denominator: f64 = numbers.iter().map(|&x| (x as f64 - mean).powi(2)).sum();
+    let numerator: f64 = numbers.iter()
+        .zip(numbers.iter().skip(lag))
+        .map(|(&a, &b)| (a as f64 - mean) * (b as f64 - mean))
+        .sum();
+    numerator / denominator
+}
+
+// NIST SP 800-22 longest-run-of-ones-in-a-block test. The values are read as
+// one bitstream (most significant bit first), cut into blocks, and the
+// distribution of each block's longest run of ones is chi-square compared to
+// its expected distribution. Returns the p-value; a small p-value means runs
+// are too long or too short. Needs at least 128 bits, otherwise returns NaN.
+pub fn longest_run_of_ones_test(numbers: &[u32]) -> f64 {
+    let n = numbers.len() * 32;
+    let (block_len, min_run, probabilities): (usize, usize, &[f64]) = if n < 128 {
+        return f64::NAN;
+    } else if n < 6272 {
+        (8, 1, &[0.2148, 0.3672, 0.2305, 0.1875])
+    } else if n < 750_000 {
+        (128, 4, &[0.1174, 0.2430, 0.2493, 0.1752, 0.1027, 0.1124])
+    } else {
+        (10_000, 10, &[0.0882, 0.2092, 0.2483, 0.1933, 0.1208, 0.0675, 0.0727])
+    };
+
+    let classes = probabilities.len();
+    let mut observed = vec![0u64; classes];
+    let mut bits = bitstream(numbers);
+    let blocks = n / block_len;
+
+    for _ in 0..blocks {
+        let mut longest = 0;
+        let mut current = 0;
+        for bit in bits.by_ref().take(block_len) {
+            if bit {
+                current += 1;
+                longest = longest.max(current);
+            } else {
+                current = 0;
+            }
+        }
+        let class = longest.clamp(min_run, min_run + classes - 1) - min_run;
+        observed[class] += 1;
+    }
+
+    let chi_square: f64 = observed.iter()
+        .zip(probabilities)
+        .map(|(&count, &p)| {
+            let expected = blocks as f64 * p;
+            (count as f64 - expected).powi(2) / expected
+        })
+        .sum();
+
+    chi_square_p_value(chi_square, (classes - 1) as f64)
+}
+
+// Histogram of run lengths over the bitstream, counting runs of zeros and ones
+// alike: entry k is how many maximal runs have length k (entry 0 is always 0).
+// For random input each length is about half as common as the one before.
+pub fn bit_run_lengths(numbers: &[u32]) -> Vec<u64> {
+    let mut histogram = vec![0u64; 1];
+    let mut record = |len: usize| {
+        if len >= histogram.len() {
+            histogram.resize(len + 1, 0);
+        }
+        histogram[len] += 1;
+    };
+
+    let mut bits = bitstream(numbers);
+    if let Some(mut previous) = bits.next() {
+        let mut current = 1;
+        for bit in bits {
+            if bit == previous {
+                current += 1;
+            } else {
+                record(current);
+                previous = bit;
+                current = 1;
+            }
+        }
+        record(current);
+    }
+    histogram
+}
+
+// Shared moment check for sampler tests: the sample mean and (population)
+// variance must each be within `tol` of the expected values. The error names
+// the moment that failed and by how much.
+pub fn assert_distribution_moments(
+    samples: &[f64],
+    expected_mean: f64,
+    expected_var: f64,
+    tol: f64,
+) -> Result<(), String> {
+    if samples.is_empty() {
+        return Err("no samples to check".to_string());
+    }
+
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+
+    let within = |actual: f64, expected: f64| (actual - expected).abs() <= tol;
+    // Checked positively so that a NaN moment is reported as a deviation
+    if !within(mean, expected_mean) {
+        return Err(format!(
+            "sample mean {:.6} deviates from expected {:.6} by more than {} ({} samples)",
+            mean, expected_mean, tol, samples.len()
+        ));
+    }
+    if !within(var, expected_var) {
+        return Err(format!(
+            "sample variance {:.6} deviates from expected {:.6} by more than {} ({} samples)",
+            var, expected_var, tol, samples.len()
+        ));
+    }
+    Ok(())
+}
+
+// Test helper: draw `samples` values from rng.gen_range(min, max) and panic
+// unless they pass a chi-square uniformity test at significance `alpha`
+pub fn assert_uniform(rng: &mut SecureRng, min: u32, max: u32, samples: usize, alpha: f64) {
+    assert_uniform_with(rng, min, max, samples, alpha, |rng| rng.gen_range(min, max));
+}
+
+// assert_uniform for any sampler meant to be uniform over [min, max), e.g.
+// `|rng| rng.gen_die(6)` over [1, 7). Spans wider than 1024 values are
+// tested over 1024 near-equal buckets; each bucket needs at least 5 expected
+// hits, so the sample must be large enough for that.
+pub fn assert_uniform_with<F>(
+    rng: &mut SecureRng,
+    min: u32,
+    max: u32,
+    samples: usize,
+    alpha: f64,
+    mut sample: F,
+) where
+    F: FnMut(&mut SecureRng) -> Result<u32, RngError>,
+{
+    assert!(min < max, "assert_uniform: empty range [{}, {})", min, max);
+    let range = (max - min) as u64;
+    let buckets = range.min(1024);
+    assert!(
+        samples as u64 >= 5 * buckets,
+        "assert_uniform: {} samples are too few for {} buckets (need at least {})",
+        samples, buckets, 5 * buckets
+    );
+
+    let mut counts = vec![0u64; buckets as usize];
+    for _ in 0..samples {
+        let value = sample(rng).unwrap_or_else(|e| panic!("assert_uniform: sampler failed: {:?}", e));
+        assert!(
+            (min..max).contains(&value),
+            "assert_uniform: {} is outside [{}, {})", value, min, max
+        );
+        counts[((value - min) as u64 * buckets / range) as usize] += 1;
+    }
+
+    // Bucket b holds the offsets o with o * buckets / range == b
+    let first_offset = |b: u64| (b * range).div_ceil(buckets);
+    let chi_square: f64 = counts.iter()
+        .enumerate()
+        .map(|(b, &count)| {
+            let b = b as u64;
+            let width = first_offset(b + 1) - first_offset(b);
+            let expected = samples as f64 * width as f64 / range as f64;
+            (count as f64 - expected).powi(2) / expected
+        })
+        .sum();
+    let p = chi_square_p_value(chi_square, (buckets - 1) as f64);
+    assert!(
+        p >= alpha,
+        "assert_uniform: values over [{}, {}) are not uniform: chi-square {:.2} over {} buckets, \
+         p-value {:.3e} < alpha {} ({} samples)",
+        min, max, chi_square, buckets, p, alpha, samples
+    );
+}
+
+// Streaming estimate of the q-quantile in constant memory (Jain & Chlamtac's
+// P-square algorithm): five markers track the minimum, q/2, q, (1+q)/2 and
+// maximum quantiles, their heights adjusted by piecewise-parabolic steps.
+#[derive(Clone, Debug)]
+pub struct P2Quantile {
+    q: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    // Panics unless 0 < q < 1
+    pub fn new(q: f64) -> Self {
+        assert!(q > 0.0 && q < 1.0, "quantile must be in (0, 1), got {}", q);
+        P2Quantile {
+            q,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * q, 1.0 + 4.0 * q, 3.0 + 2.0 * q, 5.0],
+            increments: [0.0, q / 2.0, q, (1.0 + q) / 2.0, 1.0],
+        }
+    }
+
+    pub fn update(&mut self, x: f64) {
+        // The first five observations become the initial marker heights
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        // Cell k holds x: heights[k] <= x < heights[k + 1], extending the ends
+        let k = if x < self.heights[0] {
+            self.heights[0] = x;
+            0
+        } else if x >= self.heights[4] {
+            self.heights[4] = x;
+            3
+        } else {
+            (0..4).rev().find(|&i| self.heights[i] <= x).unwrap_or(0)
+        };
+
+        for position in &mut self.positions[k + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+
+        for i in 1..4 {
+            let drift = self.desired[i] - self.positions[i];
+            let room_right = self.positions[i + 1] - self.positions[i] > 1.0;
+            let room_left = self.positions[i - 1] - self.positions[i] < -1.0;
+            if (drift >= 1.0 && room_right) || (drift <= -1.0 && room_left) {
+                let d = drift.signum();
+                let parabolic = self.parabolic(i, d);
+                let bracketed = self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1];
+                self.heights[i] = if bracketed { parabolic } else { self.linear(i, d) };
+                self.positions[i] += d;
+            }
+        }
+    }
+
+    // Current estimate; exact for fewer than five observations, NaN for none
+    pub fn estimate(&self) -> f64 {
+        if self.count >= 5 {
+            return self.heights[2];
+        }
+        if self.count == 0 {
+            return f64::NAN;
+        }
+
+        let mut seen = self.heights[..self.count].to_vec();
+        seen.sort_by(f64::total_cmp);
+        seen[((self.count - 1) as f64 * self.q).round() as usize]
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn parabolic(&self, i: usize, d: f64) -> f64 {
+        let (n, h) = (&self.positions, &self.heights);
+        h[i] + d / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + d) * (h[i + 1] - h[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - d) * (h[i] - h[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, d: f64) -> f64 {
+        let j = if d > 0.0 { i + 1 } else { i - 1 };
+        let (n, h) = (&self.positions, &self.heights);
+        h[i] + d * (h[j] - h[i]) / (n[j] - n[i])
+    }
+}
+
+// All bits of the values, most significant bit of each value first
+fn bitstream(numbers: &[u32]) -> impl Iterator<Item = bool> + '_ {
+    numbers.iter().flat_map(|&num| (0..32).rev().map(move |bit| (num >> bit) & 1 == 1))
+}
+
+// P(|Z| >= |z|) for a standard normal Z: erfc(|z| / sqrt(2)) = Q(1/2, z^2 / 2)
+pub(crate) fn two_sided_p_value(z: f64) -> f64 {
+    if z.is_nan() {
+        return f64::NAN;
+    }
+    upper_incomplete_gamma(0.5, z * z / 2.0)
+}
+
+// Upper-tail probability of a chi-square statistic with `dof` degrees of freedom
+pub(crate) fn chi_square_p_value(chi_square: f64, dof: f64) -> f64 {
+    upper_incomplete_gamma(dof / 2.0, chi_square / 2.0)
+}
+
+// Regularized upper incomplete gamma Q(a, x) (series / continued fraction)
+pub(crate) fn upper_incomplete_gamma(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+
+    let prefix = (-x + a * x.ln() - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut denominator = a;
+        for _ in 0..10_000 {
+            denominator += 1.0;
+            term *= x / denominator;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        return (1.0 - sum * prefix).max(0.0);
+    }
+
+    // Modified Lentz evaluation of the continued fraction
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..10_000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    (prefix * h).min(1.0)
+}
+
+// ln(Gamma(x)) for x > 0 (Lanczos approximation, g = 7)
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let mut series = COEFFICIENTS[0];
+    for (i, &c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        series += c / (x + i as f64);
+    }
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+// ln of the Binomial(n, p) pmf at x, with q = 1 - p passed in so callers can
+// supply it exactly. Loader's saddle-point form: the huge terms of
+// ln(n!) - ln(x!) - ln((n - x)!) cancel analytically instead of in floating
+// point, so it stays accurate for n far beyond 2^53.
+pub(crate) fn ln_binomial_pmf(x: f64, n: f64, p: f64, q: f64) -> f64 {
+    if x == 0.0 {
+        return if p < 0.1 { -bd0(n, n * q) - n * p } else { n * q.ln() };
+    }
+    if x == n {
+        return if q < 0.1 { -bd0(n, n * p) - n * q } else { n * p.ln() };
+    }
+
+    let lc = stirling_error(n) - stirling_error(x) - stirling_error(n - x) - bd0(x, n * p) - bd0(n - x, n * q);
+    let lf = (2.0 * std::f64::consts::PI).ln() + x.ln() + (-x / n).ln_1p();
+    lc - 0.5 * lf
+}
+
+// ln(n!) minus its Stirling approximation ln(sqrt(2 pi n) (n / e)^n)
+fn stirling_error(n: f64) -> f64 {
+    const S0: f64 = 1.0 / 12.0;
+    const S1: f64 = 1.0 / 360.0;
+    const S2: f64 = 1.0 / 1260.0;
+    const S3: f64 = 1.0 / 1680.0;
+    const S4: f64 = 1.0 / 1188.0;
+
+    if n <= 15.0 {
+        return ln_gamma(n + 1.0) - (n + 0.5) * n.ln() + n - 0.5 * (2.0 * std::f64::consts::PI).ln();
+    }
+
+    let nn = n * n;
+    if n > 500.0 {
+        (S0 - S1 / nn) / n
+    } else if n > 80.0 {
+        (S0 - (S1 - S2 / nn) / nn) / n
+    } else if n > 35.0 {
+        (S0 - (S1 - (S2 - S3 / nn) / nn) / nn) / n
+    } else {
+        (S0 - (S1 - (S2 - (S3 - S4 / nn) / nn) / nn) / nn) / n
+    }
+}
+
+// x ln(x / m) + m - x, by series when x is close to m where the direct form
+// would cancel
+fn bd0(x: f64, m: f64) -> f64 {
+    if (x - m).abs() < 0.1 * (x + m) {
+        let v = (x - m) / (x + m);
+        let v2 = v * v;
+        let mut sum = (x - m) * v;
+        let mut term = 2.0 * x * v;
+        for j in 1..1000 {
+            term *= v2;
+            let next = sum + term / (2 * j + 1) as f64;
+            if next == sum {
+                break;
+            }
+            sum = next;
+        }
+        return sum;
+    }
+    x * (x / m).ln() + m - x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_popcount_distribution() {
+        let zeros = popcount_distribution(&[0; 100]);
+        assert_eq!(zeros.len(), 33);
+        assert_eq!(zeros[0], 100);
+        assert_eq!(zeros.iter().sum::<u64>(), 100);
+
+        let mut rng = SecureRng::new();
+        let sample: Vec<u32> = (0..50_000).map(|_| rng.next_u32().unwrap()).collect();
+        let histogram = popcount_distribution(&sample);
+        let peak = (0..33).max_by_key(|&k| histogram[k]).unwrap();
+        assert!((15..=17).contains(&peak), "peak at {}", peak);
+        assert_eq!(histogram.iter().sum::<u64>(), 50_000);
+    }
+
+    #[test]
+    fn test_byte_entropy_of_uniform_bytes() {
+        // Every byte value exactly equally often: exactly 8 bits/byte
+        let uniform: Vec<u32> = (0..=255u8)
+            .cycle()
+            .take(256 * 64)
+            .collect::<Vec<u8>>()
+            .chunks_exact(4)
+            .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        assert!((byte_entropy(&uniform) - 8.0).abs() < 1e-9);
+
+        let mut rng = SecureRng::from_seed([1u8; 32]);
+        let sample: Vec<u32> = (0..100_000).map(|_| rng.next_u32().unwrap()).collect();
+        let entropy = byte_entropy(&sample);
+        assert!(entropy > 7.99 && entropy <= 8.0, "entropy {}", entropy);
+
+        assert_eq!(byte_entropy(&[0, 0, 0]), 0.0);
+    }
+
+    #[test]
+    fn test_monobit() {
+        let mut rng = SecureRng::from_seed([18u8; 32]);
+        let sample: Vec<u32> = (0..10_000).map(|_| rng.next_u32().unwrap()).collect();
+        assert!(monobit_test(&sample) > 0.01);
+
+        // NIST SP 800-22 example: 1011010101 has p-value 0.527089
+        let p = upper_incomplete_gamma(0.5, (2.0 / 10f64.sqrt()).powi(2) / 2.0);
+        assert!((p - 0.527_089).abs() < 1e-6);
+
+        assert!(monobit_test(&[0xffff_fff0; 1000]) < 1e-10);
+        assert_eq!(monobit_test(&[0x0f0f_0f0f; 4]), 1.0);
+    }
+
+    #[test]
+    fn test_min_entropy_mcv() {
+        assert_eq!(min_entropy_mcv(&[7u8; 1000]), 0.0);
+        assert!(min_entropy_mcv(&[7u8; 1000]).is_sign_positive());
+        assert!(min_entropy_mcv(&[1, 2]).is_sign_positive());
+
+        // Every byte value equally often: just under 8 bits once the bound is applied
+        let bytes: Vec<u8> = (0..256 * 400).map(|i| i as u8).collect();
+        let h = min_entropy_mcv(&bytes);
+        assert!(h > 7.5 && h < 8.0, "min-entropy {}", h);
+    }
+
+    #[test]
+    fn test_runs_and_autocorrelation() {
+        let mut rng = SecureRng::from_seed([12u8; 32]);
+        let sample: Vec<u32> = (0..50_000).map(|_| rng.next_u32().unwrap()).collect();
+        assert!(runs_z_score(&sample).abs() < 4.0);
+        assert!(autocorrelation(&sample, 1).abs() < 0.02);
+
+        // Alternating bits produce far too many runs
+        assert!(runs_z_score(&[0x5555_5555; 1000]) > 10.0);
+        // A slow ramp is strongly correlated with its own next value
+        let ramp: Vec<u32> = (0..1000).map(|i| i * 1000).collect();
+        assert!(autocorrelation(&ramp, 1) > 0.9);
+
+        assert_eq!(chi_square_statistic(&[10, 10, 10]), 0.0);
+        assert_eq!(chi_square_statistic(&[20, 0]), 20.0);
+    }
+
+    #[test]
+    fn test_ln_gamma_and_chi_square_tail() {
+        assert!((ln_gamma(1.0)).abs() < 1e-12);
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-12);
+
+        // Reference values: chi-square(1) at 3.841 ~ 0.05, chi-square(10) at 23.209 ~ 0.01
+        assert!((chi_square_p_value(3.841_458_820_694_124, 1.0) - 0.05).abs() < 1e-9);
+        assert!((chi_square_p_value(23.209_251_158_954_36, 10.0) - 0.01).abs() < 1e-9);
+        assert_eq!(chi_square_p_value(0.0, 3.0), 1.0);
+    }
+
+    #[test]
+    fn test_ln_binomial_pmf() {
+        // Binomial(10, 0.3) at 3 and both ends
+        assert!((ln_binomial_pmf(3.0, 10.0, 0.3, 0.7).exp() - 0.266_827_932).abs() < 1e-9);
+        assert!((ln_binomial_pmf(0.0, 10.0, 0.3, 0.7) - 10.0 * 0.7f64.ln()).abs() < 1e-12);
+        assert!((ln_binomial_pmf(10.0, 10.0, 0.3, 0.7) - 10.0 * 0.3f64.ln()).abs() < 1e-12);
+
+        // Central term at n = 2^62 is 1 / sqrt(2 pi n p q) to within 1 / (4n)
+        let n = 2f64.powi(62);
+        let expected = -0.5 * (2.0 * std::f64::consts::PI * n / 4.0).ln();
+        assert!((ln_binomial_pmf(n / 2.0, n, 0.5, 0.5) - expected).abs() < 1e-12);
+
+        // Stirling error matches ln_gamma on both sides of the series cut-over
+        for n in [1.0, 15.0, 16.0, 40.0, 100.0, 1000.0] {
+            let direct = ln_gamma(n + 1.0) - (n + 0.5) * f64::ln(n) + n - 0.5 * (2.0 * std::f64::consts::PI).ln();
+            assert!((stirling_error(n) - direct).abs() < 1e-10, "n {}", n);
+        }
+    }
+
+    #[test]
+    fn test_longest_run_of_ones() {
+        let mut rng = SecureRng::from_seed([11u8; 32]);
+        let sample: Vec<u32> = (0..10_000).map(|_| rng.next_u32().unwrap()).collect();
+        let p = longest_run_of_ones_test(&sample);
+        assert!(p > 0.01, "random sample p-value {}", p);
+
+        // 0111 repeated: no run of ones ever exceeds three
+        let capped = vec![0x7777_7777u32; 10_000];
+        let p = longest_run_of_ones_test(&capped);
+        assert!(p < 1e-6, "capped sample p-value {}", p);
+
+        assert!(longest_run_of_ones_test(&[0, 1, 2]).is_nan());
+    }
+
+    #[test]
+    fn test_assert_distribution_moments() {
+        let mut rng = SecureRng::from_seed([25u8; 32]);
+        let mut normal = vec![0.0; 100_000];
+        rng.fill_normal(&mut normal, 3.0, 2.0).unwrap();
+        assert_eq!(assert_distribution_moments(&normal, 3.0, 4.0, 0.05), Ok(()));
+
+        let shifted: Vec<f64> = normal.iter().map(|x| x + 0.5).collect();
+        let err = assert_distribution_moments(&shifted, 3.0, 4.0, 0.05).unwrap_err();
+        assert!(err.starts_with("sample mean 3.5"), "{}", err);
+        assert!(err.contains("expected 3.000000"), "{}", err);
+
+        let widened: Vec<f64> = normal.iter().map(|x| 3.0 + 1.5 * (x - 3.0)).collect();
+        let err = assert_distribution_moments(&widened, 3.0, 4.0, 0.05).unwrap_err();
+        assert!(err.starts_with("sample variance 8.9"), "{}", err);
+
+        assert!(assert_distribution_moments(&[], 0.0, 1.0, 0.1).is_err());
+        assert!(assert_distribution_moments(&[f64::NAN], 0.0, 1.0, 0.1).is_err());
+    }
+
+    #[test]
+    fn test_assert_uniform() {
+        let mut rng = SecureRng::from_seed([50u8; 32]);
+        assert_uniform(&mut rng, 10, 20, 20_000, 1e-4);
+        // Wider than the bucket limit, with buckets of unequal width
+        assert_uniform(&mut rng, 0, 1_000_003, 50_000, 1e-4);
+        assert_uniform(&mut rng, u32::MAX - 3000, u32::MAX, 50_000, 1e-4);
+        assert_uniform_with(&mut rng, 0, 37, 20_000, 1e-4, |rng| rng.gen_below(37));
+        assert_uniform_with(&mut rng, 1, 7, 20_000, 1e-4, |rng| rng.gen_die(6));
+        assert_uniform_with(&mut rng, 1, 21, 20_000, 1e-4, |rng| rng.gen_die(20));
+    }
+
+    #[test]
+    #[should_panic(expected = "are not uniform")]
+    fn test_assert_uniform_catches_modulo_bias() {
+        let mut rng = SecureRng::from_seed([51u8; 32]);
+        // Naive modulo over a span that doesn't divide 2^32 favours low values
+        let span = 3 << 30;
+        assert_uniform_with(&mut rng, 0, span, 200_000, 1e-4, |rng| Ok(rng.next_u32()? % span));
+    }
+
+    #[test]
+    fn test_p2_quantile() {
+        let mut rng = SecureRng::from_seed([31u8; 32]);
+        let mut median = P2Quantile::new(0.5);
+        let mut p99 = P2Quantile::new(0.99);
+        assert!(median.estimate().is_nan());
+
+        for _ in 0..100_000 {
+            let x = rng.next_f64().unwrap();
+            median.update(x);
+            p99.update(x);
+        }
+        assert_eq!(median.count(), 100_000);
+        assert!((median.estimate() - 0.5).abs() < 0.01, "median {}", median.estimate());
+        assert!((p99.estimate() - 0.99).abs() < 0.005, "p99 {}", p99.estimate());
+
+        // Exponential(1): 99th percentile is ln(100) ~ 4.605
+        let mut tail = P2Quantile::new(0.99);
+        for _ in 0..100_000 {
+            tail.update(rng.gen_exponential(1.0).unwrap());
+        }
+        assert!((tail.estimate() - 100f64.ln()).abs() < 0.15, "p99 {}", tail.estimate());
+
+        let mut few = P2Quantile::new(0.5);
+        for x in [3.0, 1.0, 2.0] {
+            few.update(x);
+        }
+        assert_eq!(few.estimate(), 2.0);
+    }
+
+    #[test]
+    fn test_bit_run_lengths() {
+        let alternating = bit_run_lengths(&[0xAAAA_AAAA; 1000]);
+        assert_eq!(alternating, vec![0, 32_000]);
+
+        let mut rng = SecureRng::from_seed([23u8; 32]);
+        let sample: Vec<u32> = (0..10_000).map(|_| rng.next_u32().unwrap()).collect();
+        let histogram = bit_run_lengths(&sample);
+        let total_bits: u64 = histogram.iter().enumerate().map(|(k, &c)| k as u64 * c).sum();
+        assert_eq!(total_bits, 320_000);
+        for k in 2..6 {
+            let ratio = histogram[k] as f64 / histogram[k - 1] as f64;
+            assert!((ratio - 0.5).abs() < 0.05, "ratio at {} is {}", k, ratio);
+        }
+
+        let mut expected = vec![0u64; 33];
+        expected[32] = 2;
+        assert_eq!(bit_run_lengths(&[0, u32::MAX]), expected);
+        assert_eq!(bit_run_lengths(&[]), vec![0]);
+    }
+}
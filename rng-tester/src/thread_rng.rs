@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+
+use crate::{RngError, SecureRng};
+
+thread_local! {
+    static THREAD_RNG: RefCell<SecureRng> = RefCell::new(SecureRng::new());
+}
+
+// Run `f` with this thread's lazily created OS-backed generator. Each thread
+// gets its own instance, so no locking is involved. Panics if called from
+// inside another with_thread_rng call on the same thread.
+pub fn with_thread_rng<R>(f: impl FnOnce(&mut SecureRng) -> R) -> R {
+    THREAD_RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+// with_thread_rng for the free functions: a nested call (say, from an entropy
+// callback that draws from the thread generator while it is being refilled)
+// gets RngError::Reentrancy instead of a panic or a half-updated buffer
+pub(crate) fn try_with_thread_rng<R>(
+    f: impl FnOnce(&mut SecureRng) -> Result<R, RngError>,
+) -> Result<R, RngError> {
+    THREAD_RNG.with(|rng| match rng.try_borrow_mut() {
+        Ok(mut rng) => f(&mut rng),
+        Err(_) => Err(RngError::Reentrancy),
+    })
+}
+
+// Replace this thread's generator with a deterministic one, so benchmark code
+// gets reproducible inputs from the free functions below. Calling it again
+// with the same seed restarts the same sequence. Other threads are unaffected.
+pub fn seeded_global(seed: [u8; 32]) {
+    THREAD_RNG.with(|rng| *rng.borrow_mut() = SecureRng::from_seed(seed));
+}
+
+pub fn random_u32() -> Result<u32, RngError> {
+    try_with_thread_rng(|rng| rng.next_u32())
+}
+
+pub fn random_range(min: u32, max: u32) -> Result<u32, RngError> {
+    try_with_thread_rng(|rng| rng.gen_range(min, max))
+}
+
+pub fn random_f64() -> Result<f64, RngError> {
+    try_with_thread_rng(|rng| rng.next_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_free_functions_use_per_thread_generators() {
+        random_u32().unwrap();
+        let main_reads = with_thread_rng(|rng| rng.os_reads());
+
+        let handle = thread::spawn(|| {
+            // A fresh thread starts with its own untouched generator
+            assert_eq!(with_thread_rng(|rng| rng.os_reads()), 0);
+
+            let value = random_range(10, 20).unwrap();
+            assert!((10..20).contains(&value));
+            let x = random_f64().unwrap();
+            assert!((0.0..1.0).contains(&x));
+            random_u32().unwrap();
+
+            with_thread_rng(|rng| rng.os_reads())
+        });
+        assert_eq!(handle.join().unwrap(), 1);
+
+        // The spawned thread's draws did not touch this thread's generator
+        assert_eq!(with_thread_rng(|rng| rng.os_reads()), main_reads);
+    }
+
+    #[test]
+    fn test_seeded_global_repeats() {
+        // Runs on its own thread so other tests keep an OS-backed generator
+        thread::spawn(|| {
+            seeded_global([7u8; 32]);
+            let first: Vec<u32> = (0..100).map(|_| random_u32().unwrap()).collect();
+
+            seeded_global([7u8; 32]);
+            let second: Vec<u32> = (0..100).map(|_| random_u32().unwrap()).collect();
+            assert_eq!(first, second);
+
+            let mut direct = SecureRng::from_seed([7u8; 32]);
+            assert!(first.iter().all(|&x| x == direct.next_u32().unwrap()));
+            assert_eq!(with_thread_rng(|rng| rng.os_reads()), 0);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_reentrant_use_is_an_error() {
+        use crate::EntropySource;
+        use std::cell::Cell;
+        use std::io;
+
+        thread_local! {
+            static NESTED_RESULT: Cell<Option<bool>> = const { Cell::new(None) };
+        }
+        fn recursing_source(buf: &mut [u8]) -> io::Result<()> {
+            let nested = matches!(random_u32(), Err(RngError::Reentrancy));
+            NESTED_RESULT.with(|result| result.set(Some(nested)));
+            buf.fill(0x5a);
+            Ok(())
+        }
+
+        thread::spawn(|| {
+            THREAD_RNG.with(|rng| {
+                *rng.borrow_mut() = SecureRng::builder()
+                    .source(EntropySource::Callback(recursing_source))
+                    .build();
+            });
+
+            assert_eq!(random_u32().unwrap(), 0x5a5a_5a5a);
+            assert_eq!(NESTED_RESULT.with(Cell::get), Some(true));
+        })
+        .join()
+        .unwrap();
+    }
+}